@@ -159,7 +159,20 @@ impl State {
             .await
             .unwrap();
         let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor::default(), None)
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    required_features: adapter.features()
+                        & (wgpu::Features::TIMESTAMP_QUERY
+                            | wgpu::Features::PIPELINE_CACHE
+                            | wgpu::Features::PUSH_CONSTANTS),
+                    required_limits: wgpu::Limits {
+                        max_push_constant_size: adapter.limits().max_push_constant_size,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                None,
+            )
             .await
             .unwrap();
 
@@ -185,11 +198,11 @@ impl State {
 
         let windows = Windows::new(WindowData::new(window, surface));
 
-        let device = WgpuDevice::new(device, queue);
+        let device = WgpuDevice::new(device, queue, adapter.get_info());
         let device = Arc::new(Device::new(device));
 
         let task_pool = Arc::new(TaskPool::new());
-        let resource_manager = ResourceManager::new(task_pool);
+        let resource_manager = ResourceManager::new(task_pool.clone());
 
         let mut graphic_context = GraphicContext::Uninitialization(GraphicContextParams {});
 
@@ -202,6 +215,11 @@ impl State {
 
         graphic_context.initialization(device, shader_event_receiver);
 
+        if let Some(pipeline_cache) = graphic_context.pipeline_cache_mut() {
+            pipeline_cache.set_task_pool(Some(task_pool));
+            pipeline_cache.set_disk_cache_directory(std::env::temp_dir().join("tiny-renderer"));
+        }
+
         State {
             windows,
             size,
@@ -4,6 +4,26 @@ use thiserror::Error;
 pub enum RendererError {
     #[error("ResourceNotFound, Resource Index is: {resource_index:?}")]
     ResourceNotFound { resource_index: usize },
+    #[error("ResourceBoard entry not found: {name}")]
+    ResourceBoardNotFound { name: String },
+    #[error("ResourceBoard entry {name} is {found}, expected {expected}")]
+    ResourceBoardTypeMismatch {
+        name: String,
+        expected: String,
+        found: String,
+    },
+    #[error("ResourceBoard entry {name} already points to another resource")]
+    ResourceBoardConflict { name: String },
+    #[error("Import conflict, {name} is already imported with another resource")]
+    ImportConflict { name: String },
+    #[error("SubGraph {sub_graph} input not found: {name}")]
+    SubGraphInputNotFound { sub_graph: String, name: String },
+    #[error("SubGraph {sub_graph} output not found: {name}")]
+    SubGraphOutputNotFound { sub_graph: String, name: String },
+    #[error("Device does not support {0:?}")]
+    UnsupportedFeature(wgpu::Features),
+    #[error("Could not create shader module: {0}")]
+    CreateShaderModule(String),
 }
 
 pub type Result<T, E = RendererError> = std::result::Result<T, E>;
@@ -1,22 +1,64 @@
-use std::sync::Arc;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use crate::{
     error::RendererError,
-    gfx_base::{device::Device, handle::TypeHandle, render_pass::RenderPassDescriptor},
+    gfx_base::{
+        ComputePassDescriptor, QuerySet,
+        device::{Device, QueueType},
+        handle::TypeHandle,
+        render_pass::{PassTimestampWrites, RenderPassDescriptor},
+    },
 };
 
 use super::{
-    DynRenderFn, FrameGraph, PassNode, RenderContext, ResourceTable, TransientResourceCache,
-    VirtualResource,
+    DynRenderFn, FrameGraph, PassNode, PassType, RenderContext, ResourceTable,
+    TransientResourceCache, VirtualResource,
 };
 
 #[derive(Default)]
 pub struct DevicePass {
-    logic_pass: LogicPass,
+    ///共享同一个硬件渲染通道的逻辑渲染节点
+    logic_passes: Vec<LogicPass>,
     render_pass_desc: RenderPassDescriptor,
+    queue_type: QueueType,
+    pass_type: PassType,
+    ///执行前需要等待的其他队列
+    wait_queues: Vec<QueueType>,
 }
 
 impl DevicePass {
+    pub fn queue_type(&self) -> QueueType {
+        self.queue_type
+    }
+
+    pub fn wait_queues(&self) -> &[QueueType] {
+        &self.wait_queues
+    }
+
+    pub fn label(&self) -> Option<&str> {
+        self.render_pass_desc.label.as_deref()
+    }
+
+    pub fn logic_passes(&self) -> &[LogicPass] {
+        &self.logic_passes
+    }
+
+    ///在渲染通道开始和结束时写入时间戳
+    pub fn set_timestamp_writes(&mut self, query_set: Arc<QuerySet>, index: u32) {
+        self.render_pass_desc.timestamp_writes = Some(PassTimestampWrites {
+            query_set,
+            beginning_of_pass_write_index: Some(index),
+            end_of_pass_write_index: Some(index + 1),
+        });
+    }
+
+    pub fn set_wait_queues(&mut self, wait_queues: Vec<QueueType>) {
+        self.wait_queues = wait_queues;
+    }
+
     pub fn extra(&mut self, graph: &mut FrameGraph, handle: TypeHandle<PassNode>) {
         let pass_node = graph.get_pass_node(&handle);
 
@@ -37,23 +79,56 @@ impl DevicePass {
             resource_request_array,
             resource_release_array,
             name: pass_node.name.clone(),
+            cpu_time: Duration::ZERO,
         };
 
-        self.logic_pass = logic_pass;
+        //渲染通道以其中的渲染节点命名
+        self.render_pass_desc.label = Some(match self.render_pass_desc.label.take() {
+            Some(label) => format!("{}+{}", label, pass_node.name).into(),
+            None => pass_node.name.clone().into(),
+        });
+
+        //合并的渲染节点沿用第一个渲染节点的附件和加载操作,存储操作由最后一个渲染节点决定
+        if self.logic_passes.is_empty() {
+            self.queue_type = pass_node.queue_type;
+            self.pass_type = pass_node.pass_type;
+            self.render_pass_desc.occlusion_query_set = pass_node.occlusion_query_set.take();
+            self.render_pass_desc
+                .color_attachments
+                .append(&mut pass_node.color_attachments);
+        } else {
+            for (color_attachment, merged) in self
+                .render_pass_desc
+                .color_attachments
+                .iter_mut()
+                .zip(pass_node.color_attachments.drain(..))
+            {
+                color_attachment.ops_mut().store = merged.ops().store;
+            }
+        }
 
-        self.render_pass_desc
-            .color_attachments
-            .append(&mut pass_node.color_attachments);
+        self.logic_passes.push(logic_pass);
     }
 
     pub fn begin(&self, render_context: &mut RenderContext) -> Result<(), RendererError> {
-        self.logic_pass.request_resources(
-            render_context.device,
-            render_context.transient_resource_cache,
-            &mut render_context.resource_table,
-        );
+        for logic_pass in self.logic_passes.iter() {
+            logic_pass.request_resources(
+                render_context.device,
+                render_context.transient_resource_cache,
+                &mut render_context.resource_table,
+            )?;
+        }
+
+        if self.pass_type == PassType::Compute {
+            if let Some(command_buffer) = render_context.cb_mut() {
+                command_buffer.begin_compute_pass(&ComputePassDescriptor {
+                    label: self.render_pass_desc.label.clone(),
+                    timestamp_writes: self.render_pass_desc.timestamp_writes.clone(),
+                });
+            }
 
-        let mut command_buffer = render_context.device().create_command_buffer();
+            return Ok(());
+        }
 
         let mut render_pass = render_context
             .device()
@@ -61,34 +136,62 @@ impl DevicePass {
 
         render_pass.do_init(render_context)?;
 
-        command_buffer.begin_render_pass(render_context.device(), render_pass);
-        render_context.set_cb(command_buffer);
+        if let Some(mut command_buffer) = render_context.take_cb() {
+            command_buffer.begin_render_pass(render_context.device(), render_pass);
+            render_context.set_cb(command_buffer);
+        }
 
         Ok(())
     }
 
     pub fn end(&self, render_context: &mut RenderContext) {
-        if let Some(mut command_buffer) = render_context.take_cb() {
-            command_buffer.end_render_pass();
-            render_context.device().submit(vec![command_buffer]);
+        if let Some(command_buffer) = render_context.cb_mut() {
+            match self.pass_type {
+                PassType::Render => command_buffer.end_render_pass(),
+                PassType::Compute => command_buffer.end_compute_pass(),
+            }
         }
 
-        self.logic_pass.release_resources(
-            render_context.transient_resource_cache,
-            &mut render_context.resource_table,
-        );
+        self.release_resources(render_context);
     }
 
+    fn release_resources(&self, render_context: &mut RenderContext) {
+        for logic_pass in self.logic_passes.iter() {
+            logic_pass.release_resources(
+                render_context.transient_resource_cache,
+                &mut render_context.resource_table,
+            );
+        }
+    }
+
+    ///出错时仍然结束渲染通道并释放资源,避免共享的命令缓冲停留在未结束的通道中
     pub fn execute(&mut self, render_context: &mut RenderContext) -> Result<(), RendererError> {
-        self.begin(render_context)?;
+        if let Err(e) = self.begin(render_context) {
+            self.release_resources(render_context);
+            return Err(e);
+        }
 
-        if let Some(render_fn) = self.logic_pass.render_fn.take() {
-            render_fn(render_context)?;
+        let mut result = Ok(());
+
+        for logic_pass in self.logic_passes.iter_mut() {
+            if let Some(render_fn) = logic_pass.render_fn.take() {
+                render_context.push_debug_group(&logic_pass.name);
+
+                let start = Instant::now();
+                result = render_fn(render_context);
+                logic_pass.cpu_time = start.elapsed();
+
+                render_context.pop_debug_group();
+
+                if result.is_err() {
+                    break;
+                }
+            }
         }
 
         self.end(render_context);
 
-        Ok(())
+        result
     }
 }
 
@@ -98,6 +201,8 @@ pub struct LogicPass {
     pub resource_release_array: Vec<TypeHandle<VirtualResource>>,
     pub resource_request_array: Vec<VirtualResource>,
     pub name: String,
+    ///录制命令的CPU耗时
+    pub cpu_time: Duration,
 }
 
 impl LogicPass {
@@ -106,10 +211,12 @@ impl LogicPass {
         device: &Arc<Device>,
         transient_resource_cache: &mut TransientResourceCache,
         resource_table: &mut ResourceTable,
-    ) {
+    ) -> Result<(), RendererError> {
         for resource in self.resource_request_array.iter() {
-            resource_table.request_resource(resource, device, transient_resource_cache);
+            resource_table.request_resource(resource, device, transient_resource_cache)?;
         }
+
+        Ok(())
     }
 
     pub fn release_resources(
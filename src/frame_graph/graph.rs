@@ -1,19 +1,96 @@
-use std::sync::Arc;
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    mem,
+    sync::Arc,
+};
 
 use super::{
-    DevicePass, ImportToFrameGraph, PassNode, RenderContext, Resource, ResourceBoard,
-    ResourceDescriptor, ResourceInfo, ResourceNode, ResourceNodeHandle, TypeEquals,
+    DevicePass, FrameTimings, GpuProfiler, ImportToFrameGraph, ImportedResourceState,
+    ImportedVirtualResource, PassNode, PassTiming, RenderContext, Resource, ResourceBoard,
+    ResourceDescriptor, ResourceInfo, ResourceNode, ResourceNodeHandle, ResourceState, TypeEquals,
     VirtualResource, pass_node_builder::PassNodeBuilder,
 };
-use crate::gfx_base::handle::TypeHandle;
+use crate::{
+    error::RendererError,
+    gfx_base::{
+        command_buffer::{CommandBuffer, CommandBufferInfo},
+        device::{Device, QueueType},
+        handle::TypeHandle,
+    },
+};
+
+///上一帧的编译结果,拓扑结构不变时可以直接复用
+struct CompiledFrameGraph {
+    topology_hash: u64,
+    ///排序后每个渲染节点在创建时的索引
+    order: Vec<usize>,
+    resource_request_arrays: Vec<Vec<TypeHandle<VirtualResource>>>,
+    resource_release_arrays: Vec<Vec<TypeHandle<VirtualResource>>>,
+    device_pass_groups: Vec<Vec<TypeHandle<PassNode>>>,
+    device_pass_wait_queues: Vec<Vec<QueueType>>,
+}
+
+///按队列暂存待提交的命令缓冲
+#[derive(Default)]
+struct QueueSubmissions {
+    pending: Vec<(QueueType, Vec<CommandBuffer>)>,
+}
+
+impl QueueSubmissions {
+    fn push(&mut self, queue_type: QueueType, mut command_buffer: CommandBuffer) {
+        command_buffer.finish();
+
+        match self
+            .pending
+            .iter_mut()
+            .find(|(pending_queue_type, _)| *pending_queue_type == queue_type)
+        {
+            Some((_, command_buffers)) => command_buffers.push(command_buffer),
+            None => self.pending.push((queue_type, vec![command_buffer])),
+        }
+    }
+
+    fn flush(&mut self, device: &Device, queue_type: QueueType) {
+        if let Some(index) = self
+            .pending
+            .iter()
+            .position(|(pending_queue_type, _)| *pending_queue_type == queue_type)
+        {
+            let (queue_type, command_buffers) = self.pending.remove(index);
+            device.submit(queue_type, command_buffers);
+        }
+    }
+
+    fn flush_all(&mut self, device: &Device) {
+        for (queue_type, command_buffers) in self.pending.drain(..) {
+            device.submit(queue_type, command_buffers);
+        }
+    }
+}
+
+///构建渲染节点前的帧图状态,放弃构建时恢复
+pub(crate) struct FrameGraphCheckpoint {
+    resource_count: usize,
+    resource_node_count: usize,
+    latest_resource_nodes: Vec<TypeHandle<ResourceNode>>,
+    resource_board: ResourceBoard,
+}
 
 #[derive(Default)]
 pub struct FrameGraph {
     pub(crate) pass_nodes: Vec<PassNode>,
     resources: Vec<VirtualResource>,
     resource_nodes: Vec<ResourceNode>,
+    ///每个资源当前最新版本的资源节点
+    latest_resource_nodes: Vec<TypeHandle<ResourceNode>>,
     resource_board: ResourceBoard,
     device_passes: Option<Vec<DevicePass>>,
+    ///每个命令缓冲录制的最大渲染节点数,为空时所有渲染节点录制到同一个命令缓冲
+    passes_per_command_buffer: Option<usize>,
+    ///是否跨帧保留编译结果
+    retain_compiled: bool,
+    compiled: Option<CompiledFrameGraph>,
+    profiler: GpuProfiler,
 }
 
 impl FrameGraph {
@@ -21,21 +98,138 @@ impl FrameGraph {
         self.pass_nodes = vec![];
         self.resources = vec![];
         self.resource_nodes = vec![];
+        self.latest_resource_nodes = vec![];
         self.resource_board = Default::default();
         self.device_passes = None;
     }
 
+    pub fn set_retain_compiled(&mut self, retain_compiled: bool) {
+        self.retain_compiled = retain_compiled;
+
+        if !retain_compiled {
+            self.compiled = None;
+        }
+    }
+
+    pub fn set_profiling(&mut self, enable: bool) {
+        self.profiler.set_enable(enable);
+    }
+
+    ///最近一帧已完成的渲染节点耗时
+    pub fn frame_timings(&self) -> Option<&FrameTimings> {
+        self.profiler.timings()
+    }
+
+    pub fn set_passes_per_command_buffer(&mut self, passes_per_command_buffer: Option<usize>) {
+        self.passes_per_command_buffer = passes_per_command_buffer;
+    }
+
     pub fn execute(&mut self, render_context: &mut RenderContext) {
         if self.device_passes.is_none() {
             return;
         }
 
-        let device_passes = self.device_passes.take().unwrap();
+        let mut device_passes = self.device_passes.take().unwrap();
+
+        let chunk_size = self
+            .passes_per_command_buffer
+            .unwrap_or(device_passes.len())
+            .max(1);
+
+        let device = render_context.device.clone();
+        let mut submissions = QueueSubmissions::default();
+        let mut current_queue_type = None;
+        let mut recorded = 0;
+
+        let query_set = self.profiler.begin_frame(&device, device_passes.len());
+
+        if let Some(query_set) = &query_set {
+            for (index, device_pass) in device_passes.iter_mut().enumerate() {
+                device_pass.set_timestamp_writes(query_set.clone(), index as u32 * 2);
+            }
+        }
+
+        for device_pass in device_passes.iter_mut() {
+            let queue_type = device.resolve_queue(device_pass.queue_type());
+
+            //跨队列同步点,先提交被等待队列上已录制的命令
+            for wait_queue_type in device_pass.wait_queues() {
+                let wait_queue_type = device.resolve_queue(*wait_queue_type);
+
+                if wait_queue_type == queue_type {
+                    continue;
+                }
+
+                if current_queue_type == Some(wait_queue_type) {
+                    if let Some(command_buffer) = render_context.take_cb() {
+                        submissions.push(wait_queue_type, command_buffer);
+                    }
+                    current_queue_type = None;
+                }
+
+                submissions.flush(&device, wait_queue_type);
+            }
+
+            if current_queue_type != Some(queue_type) || recorded >= chunk_size {
+                if let (Some(current_queue_type), Some(command_buffer)) =
+                    (current_queue_type, render_context.take_cb())
+                {
+                    submissions.push(current_queue_type, command_buffer);
+                }
+
+                render_context.set_cb(device.create_command_buffer(CommandBufferInfo {
+                    label: device_pass.label().map(|label| label.to_owned().into()),
+                }));
+                current_queue_type = Some(queue_type);
+                recorded = 0;
+            }
 
-        for mut device_pass in device_passes {
             if let Err(e) = device_pass.execute(render_context) {
                 println!("{}", e);
             }
+
+            recorded += 1;
+        }
+
+        if let (Some(query_set), Some(command_buffer)) = (&query_set, render_context.cb_mut()) {
+            command_buffer.resolve_query_set(query_set, device_passes.len() as u32 * 2);
+        }
+
+        if let (Some(current_queue_type), Some(command_buffer)) =
+            (current_queue_type, render_context.take_cb())
+        {
+            submissions.push(current_queue_type, command_buffer);
+        }
+
+        submissions.flush_all(&device);
+
+        //提交后开始回读导入的查询集
+        for resource in self.resources.iter() {
+            if let ResourceState::Imported(ImportedResourceState {
+                resource: ImportedVirtualResource::QuerySet(query_set),
+                ..
+            }) = &resource.state
+            {
+                query_set.request_read();
+            }
+        }
+
+        if self.profiler.is_enable() {
+            let mut timings = FrameTimings::default();
+            let mut device_pass_indices = vec![];
+
+            for (index, device_pass) in device_passes.iter().enumerate() {
+                for logic_pass in device_pass.logic_passes() {
+                    timings.passes.push(PassTiming {
+                        name: logic_pass.name.clone(),
+                        cpu_time: logic_pass.cpu_time,
+                        gpu_time: None,
+                    });
+                    device_pass_indices.push(index);
+                }
+            }
+
+            self.profiler.end_frame(timings, device_pass_indices);
         }
 
         self.reset();
@@ -77,45 +271,258 @@ impl FrameGraph {
         }
     }
 
-    fn sort(&mut self) {
+    ///返回排序后每个渲染节点在创建时的索引
+    fn sort(&mut self) -> Vec<usize> {
         self.pass_nodes
             .sort_by(|a, b| a.insert_point.cmp(&b.insert_point));
 
+        let order = self
+            .pass_nodes
+            .iter()
+            .map(|pass_node| pass_node.handle.index())
+            .collect::<Vec<_>>();
+
+        self.remap_pass_node_handles(&order);
+
+        order
+    }
+
+    ///重新分配渲染节点的句柄,并更新资源节点中记录的写入节点
+    fn remap_pass_node_handles(&mut self, order: &[usize]) {
+        let mut new_indices = vec![0; order.len()];
+
+        for (new_index, old_index) in order.iter().enumerate() {
+            new_indices[*old_index] = new_index;
+        }
+
         for index in 0..self.pass_nodes.len() {
             self.pass_nodes[index].handle = TypeHandle::new(index);
         }
+
+        for resource_node in self.resource_nodes.iter_mut() {
+            if let Some(handle) = resource_node.pass_node_writer_handle.as_mut() {
+                *handle = TypeHandle::new(new_indices[handle.index()]);
+            }
+        }
     }
 
-    fn generate_device_passes(&mut self) {
-        if self.pass_nodes.is_empty() {
-            return;
+    ///按照缓存的顺序重排渲染节点
+    fn reorder(&mut self, order: &[usize]) {
+        let mut pass_nodes = mem::take(&mut self.pass_nodes)
+            .into_iter()
+            .map(Some)
+            .collect::<Vec<_>>();
+
+        self.pass_nodes = order
+            .iter()
+            .map(|index| pass_nodes[*index].take().unwrap())
+            .collect();
+
+        self.remap_pass_node_handles(order);
+    }
+
+    fn topology_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+
+        for pass_node in self.pass_nodes.iter() {
+            pass_node.name.hash(&mut hasher);
+            pass_node.insert_point.hash(&mut hasher);
+            pass_node.queue_type.hash(&mut hasher);
+            pass_node.pass_type.hash(&mut hasher);
+            pass_node.reads.hash(&mut hasher);
+            pass_node.writes.hash(&mut hasher);
+
+            //附件的加载方式决定渲染节点能否合并,清除颜色不影响结构
+            for color_attachment in pass_node.color_attachments.iter() {
+                color_attachment.resource_handle().hash(&mut hasher);
+                color_attachment.is_load().hash(&mut hasher);
+                color_attachment.ops().store.hash(&mut hasher);
+            }
+
+            pass_node
+                .occlusion_query_set
+                .as_ref()
+                .map(|query_set| query_set.resource_handle())
+                .hash(&mut hasher);
         }
 
-        let mut device_passes = vec![];
+        for resource in self.resources.iter() {
+            resource.info.name.hash(&mut hasher);
+
+            match &resource.state {
+                ResourceState::Setup(desc) => desc.hash(&mut hasher),
+                ResourceState::Imported(state) => state.desc.hash(&mut hasher),
+            }
+        }
+
+        hasher.finish()
+    }
+
+    fn merge_device_pass_groups(&self) -> Vec<Vec<TypeHandle<PassNode>>> {
+        let mut groups: Vec<Vec<TypeHandle<PassNode>>> = vec![];
 
         for index in 0..self.pass_nodes.len() {
             let pass_node_handle = TypeHandle::new(index);
 
+            //相邻且附件相同的渲染节点合并到同一个渲染通道
+            if let Some(group) = groups.last_mut().filter(|group| {
+                self.get_pass_node(&group[0])
+                    .can_merge(self.get_pass_node(&pass_node_handle))
+            }) {
+                group.push(pass_node_handle);
+                continue;
+            }
+
+            groups.push(vec![pass_node_handle]);
+        }
+
+        groups
+    }
+
+    ///计算每个渲染通道需要等待的其他队列,覆盖写后读、读后写和写后写
+    ///
+    ///同步点只保证被等待队列上之前录制的命令先提交,不是GPU上的信号量。
+    ///wgpu只有一个队列,提交顺序就是执行顺序,所以目前只有图形队列可用,其他队列回退到图形队列
+    fn compute_sync_points(&self, groups: &[Vec<TypeHandle<PassNode>>]) -> Vec<Vec<QueueType>> {
+        let accesses = self
+            .pass_nodes
+            .iter()
+            .map(|pass_node| {
+                let resource_handles = |handles: &[TypeHandle<ResourceNode>]| {
+                    handles
+                        .iter()
+                        .map(|handle| self.get_resource_node(handle).resource_handle)
+                        .collect::<Vec<_>>()
+                };
+
+                (
+                    resource_handles(&pass_node.reads),
+                    resource_handles(&pass_node.writes),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        groups
+            .iter()
+            .map(|group| {
+                let queue_type = self.get_pass_node(&group[0]).queue_type;
+                let mut wait_queues = vec![];
+
+                for pass_node_handle in group.iter() {
+                    let (reads, writes) = &accesses[pass_node_handle.index()];
+
+                    for (index, (other_reads, other_writes)) in
+                        accesses[..pass_node_handle.index()].iter().enumerate()
+                    {
+                        let other_queue_type = self.pass_nodes[index].queue_type;
+
+                        if other_queue_type == queue_type || wait_queues.contains(&other_queue_type)
+                        {
+                            continue;
+                        }
+
+                        let conflict = reads.iter().any(|handle| other_writes.contains(handle))
+                            || writes.iter().any(|handle| {
+                                other_reads.contains(handle) || other_writes.contains(handle)
+                            });
+
+                        if conflict {
+                            wait_queues.push(other_queue_type);
+                        }
+                    }
+                }
+
+                wait_queues
+            })
+            .collect()
+    }
+
+    fn generate_device_passes(
+        &mut self,
+        groups: &[Vec<TypeHandle<PassNode>>],
+        wait_queues: &[Vec<QueueType>],
+    ) {
+        if self.pass_nodes.is_empty() {
+            return;
+        }
+
+        let mut device_passes = vec![];
+
+        for (group, wait_queues) in groups.iter().zip(wait_queues.iter()) {
             let mut device_pass = DevicePass::default();
 
-            device_pass.extra(self, pass_node_handle);
+            for pass_node_handle in group.iter() {
+                device_pass.extra(self, *pass_node_handle);
+            }
+
+            device_pass.set_wait_queues(wait_queues.clone());
+
             device_passes.push(device_pass);
         }
 
         self.device_passes = Some(device_passes);
     }
 
+    fn apply_compiled(&mut self, compiled: &CompiledFrameGraph) {
+        self.reorder(&compiled.order);
+
+        for (index, pass_node) in self.pass_nodes.iter_mut().enumerate() {
+            pass_node.resource_request_array = compiled.resource_request_arrays[index].clone();
+            pass_node.resource_release_array = compiled.resource_release_arrays[index].clone();
+        }
+
+        self.generate_device_passes(
+            &compiled.device_pass_groups,
+            &compiled.device_pass_wait_queues,
+        );
+    }
+
     pub fn compile(&mut self) {
         if self.pass_nodes.is_empty() {
             return;
         }
 
-        self.sort();
+        let topology_hash = self.retain_compiled.then(|| self.topology_hash());
+
+        if let Some(compiled) = self
+            .compiled
+            .take()
+            .filter(|compiled| Some(compiled.topology_hash) == topology_hash)
+        {
+            self.apply_compiled(&compiled);
+            self.compiled = Some(compiled);
+            return;
+        }
+
+        let order = self.sort();
         //todo cull
 
         self.compute_resource_lifetime();
 
-        self.generate_device_passes();
+        let device_pass_groups = self.merge_device_pass_groups();
+
+        let device_pass_wait_queues = self.compute_sync_points(&device_pass_groups);
+
+        self.generate_device_passes(&device_pass_groups, &device_pass_wait_queues);
+
+        if let Some(topology_hash) = topology_hash {
+            self.compiled = Some(CompiledFrameGraph {
+                topology_hash,
+                order,
+                resource_request_arrays: self
+                    .pass_nodes
+                    .iter()
+                    .map(|pass_node| pass_node.resource_request_array.clone())
+                    .collect(),
+                resource_release_arrays: self
+                    .pass_nodes
+                    .iter()
+                    .map(|pass_node| pass_node.resource_release_array.clone())
+                    .collect(),
+                device_pass_groups,
+                device_pass_wait_queues,
+            });
+        }
     }
 }
 
@@ -124,6 +531,28 @@ impl FrameGraph {
         &self.resource_board
     }
 
+    pub fn get_resource_board_mut(&mut self) -> &mut ResourceBoard {
+        &mut self.resource_board
+    }
+
+    ///将资源节点以名称导出到当前命名空间
+    pub fn export<ResourceType: 'static>(
+        &mut self,
+        name: &str,
+        handle: ResourceNodeHandle<ResourceType>,
+    ) -> Result<(), RendererError> {
+        self.resource_board.put(name, handle)
+    }
+
+    ///将资源面板中的条目指向资源的最新版本
+    pub fn update_board<ResourceType: 'static>(
+        &mut self,
+        name: &str,
+        handle: ResourceNodeHandle<ResourceType>,
+    ) -> Result<(), RendererError> {
+        self.resource_board.update(name, handle)
+    }
+
     pub fn get_pass_node_mut(&mut self, handle: &TypeHandle<PassNode>) -> &mut PassNode {
         &mut self.pass_nodes[handle.index()]
     }
@@ -143,6 +572,44 @@ impl FrameGraph {
         &mut self.resource_nodes[handle.index()]
     }
 
+    pub fn get_latest_resource_node(
+        &self,
+        resource_handle: &TypeHandle<VirtualResource>,
+    ) -> TypeHandle<ResourceNode> {
+        self.latest_resource_nodes[resource_handle.index()]
+    }
+
+    ///按执行顺序在渲染节点之前写入的最新版本,执行顺序按insert_point排序,相同时按添加顺序
+    pub fn get_latest_resource_node_before(
+        &self,
+        resource_handle: &TypeHandle<VirtualResource>,
+        pass_node: &PassNode,
+    ) -> TypeHandle<ResourceNode> {
+        let runs_before = |resource_node: &ResourceNode| match resource_node.pass_node_writer_handle
+        {
+            None => true,
+            Some(writer) if writer == pass_node.handle => false,
+            Some(writer) => {
+                let insert_point = self.get_pass_node(&writer).insert_point;
+                (insert_point, writer.index()) < (pass_node.insert_point, pass_node.handle.index())
+            }
+        };
+
+        let latest = self.get_latest_resource_node(resource_handle);
+
+        if runs_before(self.get_resource_node(&latest)) {
+            return latest;
+        }
+
+        self.resource_nodes
+            .iter()
+            .rev()
+            .filter(|resource_node| resource_node.resource_handle == *resource_handle)
+            .find(|resource_node| runs_before(resource_node))
+            .map(|resource_node| resource_node.handle)
+            .unwrap_or(latest)
+    }
+
     pub fn get_resource(&self, handle: &TypeHandle<VirtualResource>) -> &VirtualResource {
         &self.resources[handle.index()]
     }
@@ -154,8 +621,65 @@ impl FrameGraph {
         &mut self.resources[handle.index()]
     }
 
+    ///子帧图中的渲染节点名称带有命名空间前缀
     pub fn create_pass_node_builder(&mut self, insert_point: u32, name: &str) -> PassNodeBuilder {
-        PassNodeBuilder::new(insert_point, name, self)
+        let name = self.resource_board.scoped_name(name);
+        PassNodeBuilder::new(insert_point, &name, self)
+    }
+
+    ///添加渲染节点,setup声明资源并返回渲染节点数据,execute执行时获取该数据
+    pub fn add_pass<PassData>(
+        &mut self,
+        insert_point: u32,
+        name: &str,
+        setup: impl FnOnce(&mut PassNodeBuilder) -> Result<PassData, RendererError>,
+        execute: impl (FnOnce(&PassData, &mut RenderContext) -> Result<(), RendererError>) + 'static,
+    ) -> Result<PassData, RendererError>
+    where
+        PassData: Clone + 'static,
+    {
+        let mut builder = self.create_pass_node_builder(insert_point, name);
+
+        let pass_data = match setup(&mut builder) {
+            Ok(pass_data) => pass_data,
+            Err(e) => {
+                builder.cancel();
+                return Err(e);
+            }
+        };
+
+        let output = pass_data.clone();
+
+        builder.render(move |render_context| execute(&pass_data, render_context));
+
+        Ok(output)
+    }
+
+    pub(crate) fn checkpoint(&self) -> FrameGraphCheckpoint {
+        FrameGraphCheckpoint {
+            resource_count: self.resources.len(),
+            resource_node_count: self.resource_nodes.len(),
+            latest_resource_nodes: self.latest_resource_nodes.clone(),
+            resource_board: self.resource_board.clone(),
+        }
+    }
+
+    ///撤销检查点之后创建的资源、写入的版本和资源面板的修改
+    pub(crate) fn rollback(&mut self, checkpoint: FrameGraphCheckpoint) {
+        self.resources.truncate(checkpoint.resource_count);
+        self.resource_nodes.truncate(checkpoint.resource_node_count);
+        self.latest_resource_nodes = checkpoint.latest_resource_nodes;
+        self.resource_board = checkpoint.resource_board;
+
+        for (resource, handle) in self
+            .resources
+            .iter_mut()
+            .zip(self.latest_resource_nodes.iter())
+        {
+            resource
+                .info
+                .set_version(self.resource_nodes[handle.index()].version);
+        }
     }
 
     pub(crate) fn create_resource_node(
@@ -170,6 +694,12 @@ impl FrameGraph {
         self.resource_nodes
             .push(ResourceNode::new(handle, resource_handle, version));
 
+        if resource_handle.index() < self.latest_resource_nodes.len() {
+            self.latest_resource_nodes[resource_handle.index()] = handle;
+        } else {
+            self.latest_resource_nodes.push(handle);
+        }
+
         handle
     }
 
@@ -178,21 +708,33 @@ impl FrameGraph {
         name: &str,
         resource: Arc<ResourceType>,
         desc: ResourceType::Descriptor,
-    ) -> ResourceNodeHandle<ResourceType>
+    ) -> Result<ResourceNodeHandle<ResourceType>, RendererError>
     where
         ResourceType: ImportToFrameGraph,
     {
-        if let Some(raw_handle) = self.resource_board.get(name) {
-            return ResourceNodeHandle::new(
-                raw_handle.resource_node_handle(),
-                raw_handle.resource_handle(),
-            );
+        let imported_resource = ImportToFrameGraph::import(resource);
+
+        //同名导入只允许指向同一个资源
+        if let Some(entry) = self.resource_board.get_entry(name) {
+            let handle = entry.typed::<ResourceType>(name)?;
+
+            let same_resource = match &self.get_resource(&handle.resource_handle()).state {
+                ResourceState::Imported(state) => state.resource.ptr_eq(&imported_resource),
+                ResourceState::Setup(_) => false,
+            };
+
+            if !same_resource {
+                return Err(RendererError::ImportConflict {
+                    name: name.to_owned(),
+                });
+            }
+
+            return Ok(handle);
         }
 
-        let imported_resource = ImportToFrameGraph::import(resource);
         let resource_handle = TypeHandle::new(self.resources.len());
         let resource: VirtualResource = VirtualResource::new_imported::<ResourceType>(
-            name,
+            &self.resource_board.scoped_name(name),
             resource_handle,
             desc,
             imported_resource,
@@ -205,9 +747,9 @@ impl FrameGraph {
 
         let handle = ResourceNodeHandle::new(handle, resource_handle);
 
-        self.resource_board.put(name, handle.raw());
+        self.resource_board.put(name, handle.clone())?;
 
-        handle
+        Ok(handle)
     }
 
     pub fn create<DescriptorType>(
@@ -224,7 +766,7 @@ impl FrameGraph {
         let resource_handle = TypeHandle::new(self.resources.len());
 
         let resource: VirtualResource = VirtualResource::new_setuped::<DescriptorType::Resource>(
-            name,
+            &self.resource_board.scoped_name(name),
             resource_handle,
             TypeEquals::same(desc),
         );
@@ -237,3 +779,264 @@ impl FrameGraph {
         ResourceNodeHandle::new(handle, resource_handle)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        error::RendererError,
+        frame_graph::{ResourceNodeHandle, ResourceNodeRef},
+        gfx_base::{Buffer, BufferInfo, ColorAttachmentInfo, TextureView, handle::TypeHandle},
+    };
+
+    use super::FrameGraph;
+
+    fn buffer_info() -> BufferInfo {
+        BufferInfo {
+            label: None,
+            size: 64,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        }
+    }
+
+    #[test]
+    fn write_creates_new_version() {
+        let mut graph = FrameGraph::default();
+        let buffer = graph.create("buffer", buffer_info());
+
+        let written = graph
+            .add_pass(
+                0,
+                "write",
+                |builder| Ok(builder.write(buffer.clone()).handle()),
+                |_, _| Ok(()),
+            )
+            .unwrap();
+
+        assert!(written.resource_handle() == buffer.resource_handle());
+        assert!(written.resource_node_handle() != buffer.resource_node_handle());
+        assert_eq!(
+            graph
+                .get_resource_node(&buffer.resource_node_handle())
+                .version,
+            0
+        );
+        assert_eq!(
+            graph
+                .get_resource_node(&written.resource_node_handle())
+                .version,
+            1
+        );
+        assert!(
+            graph.get_latest_resource_node(&buffer.resource_handle())
+                == written.resource_node_handle()
+        );
+
+        let writer = graph
+            .get_resource_node(&written.resource_node_handle())
+            .pass_node_writer_handle;
+        assert!(writer == Some(graph.pass_nodes[0].handle));
+    }
+
+    #[test]
+    fn stale_read_uses_latest_version() {
+        let mut graph = FrameGraph::default();
+        let buffer = graph.create("buffer", buffer_info());
+
+        let written = graph
+            .add_pass(
+                0,
+                "write",
+                |builder| Ok(builder.write(buffer.clone()).handle()),
+                |_, _| Ok(()),
+            )
+            .unwrap();
+
+        let read = graph
+            .add_pass(
+                0,
+                "read",
+                |builder| Ok(builder.read(buffer.clone()).handle()),
+                |_, _| Ok(()),
+            )
+            .unwrap();
+
+        assert!(read.resource_node_handle() == written.resource_node_handle());
+        assert!(graph.pass_nodes[1].reads == vec![written.resource_node_handle()]);
+    }
+
+    #[test]
+    fn read_ignores_version_written_by_later_pass() {
+        let mut graph = FrameGraph::default();
+        let buffer = graph.create("buffer", buffer_info());
+        graph.export("buffer", buffer.clone()).unwrap();
+
+        let first = graph
+            .add_pass(
+                0,
+                "first",
+                |builder| Ok(builder.write(buffer.clone()).handle()),
+                |_, _| Ok(()),
+            )
+            .unwrap();
+
+        graph
+            .add_pass(
+                10,
+                "later",
+                |builder| Ok(builder.write(first.clone()).handle()),
+                |_, _| Ok(()),
+            )
+            .unwrap();
+
+        let read = graph
+            .add_pass(
+                5,
+                "read",
+                |builder| Ok(builder.read(buffer.clone()).handle()),
+                |_, _| Ok(()),
+            )
+            .unwrap();
+
+        let read_from_board = graph
+            .add_pass(
+                5,
+                "read_from_board",
+                |builder| {
+                    builder
+                        .read_from_board::<Buffer>("buffer")
+                        .map(|read| read.handle())
+                },
+                |_, _| Ok(()),
+            )
+            .unwrap();
+
+        assert!(read.resource_node_handle() == first.resource_node_handle());
+        assert!(read_from_board.resource_node_handle() == first.resource_node_handle());
+    }
+
+    #[test]
+    fn write_updates_board() {
+        let mut graph = FrameGraph::default();
+        let buffer = graph.create("buffer", buffer_info());
+        graph.export("buffer", buffer.clone()).unwrap();
+
+        let written = graph
+            .add_pass(
+                0,
+                "write",
+                |builder| Ok(builder.write(buffer.clone()).handle()),
+                |_, _| Ok(()),
+            )
+            .unwrap();
+
+        let exported = graph.get_resource_board().get::<Buffer>("buffer").unwrap();
+        assert!(exported.resource_node_handle() == written.resource_node_handle());
+    }
+
+    #[test]
+    fn failed_setup_discards_pass() {
+        let mut graph = FrameGraph::default();
+
+        let result = graph.add_pass(
+            0,
+            "read",
+            |builder| {
+                builder
+                    .read_from_board::<Buffer>("missing")
+                    .map(|read| read.handle())
+            },
+            |_: &ResourceNodeHandle<Buffer>, _| Ok(()),
+        );
+
+        assert!(matches!(
+            result,
+            Err(RendererError::ResourceBoardNotFound { .. })
+        ));
+        assert!(graph.pass_nodes.is_empty());
+    }
+
+    fn attachment_graph(load: wgpu::LoadOp<wgpu::Color>) -> FrameGraph {
+        let mut graph = FrameGraph::default();
+
+        graph
+            .add_pass(
+                0,
+                "draw",
+                |builder| {
+                    builder.add_attachment_info(ColorAttachmentInfo::swap_chain_with_ops(
+                        ResourceNodeRef::new(ResourceNodeHandle::<TextureView>::new(
+                            TypeHandle::new(0),
+                            TypeHandle::new(0),
+                        )),
+                        wgpu::Operations {
+                            load,
+                            store: wgpu::StoreOp::Store,
+                        },
+                    ));
+                    Ok(())
+                },
+                |_, _| Ok(()),
+            )
+            .unwrap();
+
+        graph
+    }
+
+    #[test]
+    fn failed_setup_rolls_back_writes() {
+        let mut graph = FrameGraph::default();
+        let buffer = graph.create("buffer", buffer_info());
+        graph.export("buffer", buffer.clone()).unwrap();
+
+        graph
+            .add_pass(
+                0,
+                "read",
+                |builder| Ok(builder.read(buffer.clone()).handle()),
+                |_, _| Ok(()),
+            )
+            .unwrap();
+
+        let result = graph.add_pass(
+            0,
+            "write",
+            |builder| {
+                builder.write(buffer.clone());
+                builder.create("temp", buffer_info());
+                builder.read_from_board::<Buffer>("missing")?;
+                Ok(())
+            },
+            |_, _| Ok(()),
+        );
+
+        assert!(result.is_err());
+        assert_eq!(graph.pass_nodes.len(), 1);
+        assert_eq!(graph.resources.len(), 1);
+        assert_eq!(graph.resource_nodes.len(), 1);
+        assert_eq!(
+            graph.get_resource(&buffer.resource_handle()).info.version(),
+            0
+        );
+        assert!(
+            graph.get_latest_resource_node(&buffer.resource_handle())
+                == buffer.resource_node_handle()
+        );
+
+        let exported = graph.get_resource_board().get::<Buffer>("buffer").unwrap();
+        assert!(exported.resource_node_handle() == buffer.resource_node_handle());
+
+        graph.compile();
+    }
+
+    #[test]
+    fn topology_hash_includes_load_op() {
+        let load = attachment_graph(wgpu::LoadOp::Load).topology_hash();
+        let clear = attachment_graph(wgpu::LoadOp::Clear(wgpu::Color::BLACK)).topology_hash();
+        let clear_other_color =
+            attachment_graph(wgpu::LoadOp::Clear(wgpu::Color::WHITE)).topology_hash();
+
+        assert_ne!(load, clear);
+        assert_eq!(clear, clear_other_color);
+    }
+}
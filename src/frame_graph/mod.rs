@@ -2,19 +2,25 @@ mod device_pass;
 mod graph;
 mod pass_node;
 mod pass_node_builder;
+mod profiler;
+mod push_constant_buffer;
 mod render_context;
 mod resource;
 mod resource_board;
 mod resource_node;
 mod resource_table;
+mod sub_graph;
 mod transient_resource_cache;
 
 pub use device_pass::*;
 pub use graph::*;
 pub use pass_node::*;
+pub use profiler::*;
+pub use push_constant_buffer::*;
 pub use render_context::*;
 pub use resource::*;
 pub use resource_board::*;
 pub use resource_node::*;
 pub use resource_table::*;
+pub use sub_graph::*;
 pub use transient_resource_cache::*;
@@ -1,10 +1,25 @@
-use crate::gfx_base::{BindGroupRef, ColorAttachmentInfo, handle::TypeHandle};
+use tracing::warn;
+
+use crate::{
+    error::RendererError,
+    gfx_base::{
+        BindGroupRef, BindingResourceInfo, ColorAttachmentInfo, QuerySet, device::QueueType,
+        handle::TypeHandle,
+    },
+};
 
 use super::{
     DynRenderFn, FrameGraph, GpuRead, GpuWrite, ResourceNode, ResourceNodeHandle, ResourceNodeRef,
     VirtualResource,
 };
 
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PassType {
+    #[default]
+    Render,
+    Compute,
+}
+
 pub struct PassNode {
     pub name: String,
     pub handle: TypeHandle<PassNode>,
@@ -16,9 +31,73 @@ pub struct PassNode {
     pub resource_release_array: Vec<TypeHandle<VirtualResource>>,
     pub color_attachments: Vec<ColorAttachmentInfo>,
     pub bind_groups: Vec<BindGroupRef>,
+    ///渲染节点提交的队列
+    pub queue_type: QueueType,
+    ///渲染通道使用的遮挡查询集
+    pub occlusion_query_set: Option<ResourceNodeRef<QuerySet, GpuWrite>>,
+    pub pass_type: PassType,
 }
 
 impl PassNode {
+    ///另一个渲染节点的附件与此渲染节点完全相同、加载附件原有内容,且不会采样这些附件时可以合并到同一个渲染通道
+    pub fn can_merge(&self, other: &PassNode) -> bool {
+        if self.pass_type != PassType::Render
+            || other.pass_type != PassType::Render
+            || self.queue_type != other.queue_type
+            || self.color_attachments.is_empty()
+            || self.color_attachments.len() != other.color_attachments.len()
+        {
+            return false;
+        }
+
+        let same_attachments = self
+            .color_attachments
+            .iter()
+            .zip(other.color_attachments.iter())
+            .all(|(a, b)| a.resource_handle() == b.resource_handle() && b.is_load());
+
+        if !same_attachments {
+            return false;
+        }
+
+        let same_occlusion_query_set = match (&self.occlusion_query_set, &other.occlusion_query_set)
+        {
+            (None, None) => true,
+            (Some(a), Some(b)) => a.resource_handle() == b.resource_handle(),
+            _ => false,
+        };
+
+        if !same_occlusion_query_set {
+            return false;
+        }
+
+        !other.bind_groups.iter().any(|bind_group| {
+            bind_group.entries.iter().any(|entry| {
+                let resource_handle = match &entry.resource {
+                    BindingResourceInfo::TextureView(handle) => handle.resource_handle(),
+                    BindingResourceInfo::StorageTexture(handle) => handle.resource_handle(),
+                    _ => return false,
+                };
+
+                self.color_attachments
+                    .iter()
+                    .any(|attachment| attachment.resource_handle() == resource_handle)
+            })
+        })
+    }
+
+    pub fn set_queue_type(&mut self, queue_type: QueueType) {
+        self.queue_type = queue_type;
+    }
+
+    pub fn set_pass_type(&mut self, pass_type: PassType) {
+        self.pass_type = pass_type;
+    }
+
+    pub fn set_occlusion_query_set(&mut self, query_set: ResourceNodeRef<QuerySet, GpuWrite>) {
+        self.occlusion_query_set = Some(query_set);
+    }
+
     pub fn add_bind_group(&mut self, bind_group: BindGroupRef) {
         self.bind_groups.push(bind_group);
     }
@@ -45,45 +124,58 @@ impl PassNode {
 
         self.writes.push(new_resource_node_handle);
 
-        ResourceNodeRef::new(ResourceNodeHandle::new(
-            new_resource_node_handle,
-            resource_handle,
-        ))
+        let new_handle = ResourceNodeHandle::new(new_resource_node_handle, resource_handle);
+
+        graph
+            .get_resource_board_mut()
+            .update_resource_node(new_handle.raw());
+
+        ResourceNodeRef::new(new_handle)
     }
 
-    pub fn read_from_board<ResourceType>(
+    pub fn read_from_board<ResourceType: 'static>(
         &mut self,
         graph: &FrameGraph,
         name: &str,
-    ) -> Option<ResourceNodeRef<ResourceType, GpuRead>> {
-        if let Some(handle) = graph.get_resource_board().get(name) {
-            if !self.reads.contains(&handle.resource_node_handle()) {
-                self.reads.push(handle.resource_node_handle());
-            }
-
-            Some(ResourceNodeRef::new(handle.clone().into()))
-        } else {
-            None
-        }
+    ) -> Result<ResourceNodeRef<ResourceType, GpuRead>, RendererError> {
+        let handle = graph.get_resource_board().get::<ResourceType>(name)?;
+
+        Ok(self.read(graph, handle))
     }
 
+    ///绑定到执行顺序上在此渲染节点之前写入的最新版本,
+    ///读取旧版本或之后才执行的渲染节点写入的版本时给出警告
     pub fn read<ResourceType>(
         &mut self,
         graph: &FrameGraph,
         resource_node_handle: ResourceNodeHandle<ResourceType>,
     ) -> ResourceNodeRef<ResourceType, GpuRead> {
-        let resource_node_handle = resource_node_handle.resource_node_handle();
+        let resource_handle = resource_node_handle.resource_handle();
+        let latest_resource_node_handle =
+            graph.get_latest_resource_node_before(&resource_handle, self);
+
+        if resource_node_handle.resource_node_handle() != latest_resource_node_handle {
+            let resource = graph.get_resource(&resource_handle);
 
-        if !self.reads.contains(&resource_node_handle) {
-            self.reads.push(resource_node_handle);
+            warn!(
+                "pass {} reads version {} of resource {}, binds to version {} written before it runs",
+                self.name,
+                graph
+                    .get_resource_node(&resource_node_handle.resource_node_handle())
+                    .version,
+                resource.info.name,
+                graph
+                    .get_resource_node(&latest_resource_node_handle)
+                    .version,
+            );
         }
 
-        let resource_handle = graph
-            .get_resource_node(&resource_node_handle)
-            .resource_handle;
+        if !self.reads.contains(&latest_resource_node_handle) {
+            self.reads.push(latest_resource_node_handle);
+        }
 
         ResourceNodeRef::new(ResourceNodeHandle::new(
-            resource_node_handle,
+            latest_resource_node_handle,
             resource_handle,
         ))
     }
@@ -100,6 +192,80 @@ impl PassNode {
             resource_release_array: vec![],
             color_attachments: vec![],
             bind_groups: vec![],
+            queue_type: QueueType::default(),
+            occlusion_query_set: None,
+            pass_type: PassType::default(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        frame_graph::{ResourceNodeHandle, ResourceNodeRef},
+        gfx_base::{ColorAttachmentInfo, TextureView, handle::TypeHandle},
+    };
+
+    use super::{PassNode, PassType};
+
+    fn attachment(resource: usize, load: wgpu::LoadOp<wgpu::Color>) -> ColorAttachmentInfo {
+        ColorAttachmentInfo::swap_chain_with_ops(
+            ResourceNodeRef::new(ResourceNodeHandle::<TextureView>::new(
+                TypeHandle::new(resource),
+                TypeHandle::new(resource),
+            )),
+            wgpu::Operations {
+                load,
+                store: wgpu::StoreOp::Store,
+            },
+        )
+    }
+
+    fn pass_node(index: usize, attachment: ColorAttachmentInfo) -> PassNode {
+        let mut pass_node = PassNode::new(0, "pass", TypeHandle::new(index));
+        pass_node.add_attachment(attachment);
+        pass_node
+    }
+
+    #[test]
+    fn merge_pass_loading_same_attachment() {
+        let first = pass_node(0, attachment(0, wgpu::LoadOp::Clear(wgpu::Color::BLACK)));
+        let second = pass_node(1, attachment(0, wgpu::LoadOp::Load));
+
+        assert!(first.can_merge(&second));
+    }
+
+    #[test]
+    fn not_merge_pass_clearing_attachment() {
+        let first = pass_node(0, attachment(0, wgpu::LoadOp::Load));
+        let second = pass_node(1, attachment(0, wgpu::LoadOp::Clear(wgpu::Color::BLACK)));
+
+        assert!(!first.can_merge(&second));
+    }
+
+    #[test]
+    fn not_merge_pass_with_different_attachment() {
+        let first = pass_node(0, attachment(0, wgpu::LoadOp::Load));
+        let second = pass_node(1, attachment(1, wgpu::LoadOp::Load));
+
+        assert!(!first.can_merge(&second));
+    }
+
+    #[test]
+    fn not_merge_compute_pass() {
+        let first = pass_node(0, attachment(0, wgpu::LoadOp::Load));
+        let mut second = pass_node(1, attachment(0, wgpu::LoadOp::Load));
+        second.set_pass_type(PassType::Compute);
+
+        assert!(!first.can_merge(&second));
+        assert!(!second.can_merge(&first));
+    }
+
+    #[test]
+    fn not_merge_pass_without_attachment() {
+        let first = PassNode::new(0, "pass", TypeHandle::new(0));
+        let second = PassNode::new(0, "pass", TypeHandle::new(1));
+
+        assert!(!first.can_merge(&second));
+    }
+}
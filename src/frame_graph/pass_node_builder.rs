@@ -2,17 +2,21 @@ use std::sync::Arc;
 
 use crate::{
     error::RendererError,
-    gfx_base::{BindGroupRef, color_attachment::ColorAttachmentInfo, handle::TypeHandle},
+    gfx_base::{
+        BindGroupRef, QuerySet, color_attachment::ColorAttachmentInfo, device::QueueType,
+        handle::TypeHandle,
+    },
 };
 
 use super::{
-    FrameGraph, GpuRead, GpuWrite, ImportToFrameGraph, PassNode, RenderContext, Resource,
-    ResourceDescriptor, ResourceNodeHandle, ResourceNodeRef, TypeEquals,
+    FrameGraph, FrameGraphCheckpoint, GpuRead, GpuWrite, ImportToFrameGraph, PassNode, PassType,
+    RenderContext, Resource, ResourceDescriptor, ResourceNodeHandle, ResourceNodeRef, TypeEquals,
 };
 
 pub struct PassNodeBuilder<'a> {
     graph: &'a mut FrameGraph,
     pass_node: Option<PassNode>,
+    checkpoint: Option<FrameGraphCheckpoint>,
 }
 
 impl Drop for PassNodeBuilder<'_> {
@@ -22,6 +26,22 @@ impl Drop for PassNodeBuilder<'_> {
 }
 
 impl<'a> PassNodeBuilder<'a> {
+    pub fn set_queue_type(&mut self, queue_type: QueueType) {
+        self.pass_node.as_mut().unwrap().set_queue_type(queue_type);
+    }
+
+    ///计算节点不会与其他渲染节点合并
+    pub fn set_pass_type(&mut self, pass_type: PassType) {
+        self.pass_node.as_mut().unwrap().set_pass_type(pass_type);
+    }
+
+    pub fn set_occlusion_query_set(&mut self, query_set: ResourceNodeRef<QuerySet, GpuWrite>) {
+        self.pass_node
+            .as_mut()
+            .unwrap()
+            .set_occlusion_query_set(query_set);
+    }
+
     pub fn add_bind_group(&mut self, bind_group: BindGroupRef) {
         self.pass_node.as_mut().unwrap().add_bind_group(bind_group);
     }
@@ -46,24 +66,36 @@ impl<'a> PassNodeBuilder<'a> {
 
     pub fn new(insert_point: u32, name: &str, graph: &'a mut FrameGraph) -> Self {
         let handle = TypeHandle::new(graph.pass_nodes.len());
+        let checkpoint = graph.checkpoint();
         Self {
             graph,
             pass_node: Some(PassNode::new(insert_point, name, handle)),
+            checkpoint: Some(checkpoint),
+        }
+    }
+
+    ///放弃构建此渲染节点,撤销构建过程中创建的资源和写入的版本
+    pub fn cancel(mut self) {
+        self.pass_node.take();
+
+        if let Some(checkpoint) = self.checkpoint.take() {
+            self.graph.rollback(checkpoint);
         }
     }
 
     fn build(&mut self) {
-        assert!(self.pass_node.as_ref().unwrap().render_fn.is_some());
+        if let Some(pass_node) = self.pass_node.take() {
+            assert!(pass_node.render_fn.is_some());
 
-        let pass_node = self.pass_node.take().unwrap();
-        self.graph.pass_nodes.push(pass_node);
+            self.graph.pass_nodes.push(pass_node);
+        }
     }
 
     pub fn import<ResourceType>(
         &mut self,
         name: &str,
         resource: Arc<ResourceType>,
-    ) -> ResourceNodeHandle<ResourceType>
+    ) -> Result<ResourceNodeHandle<ResourceType>, RendererError>
     where
         ResourceType: ImportToFrameGraph,
     {
@@ -85,10 +117,26 @@ impl<'a> PassNodeBuilder<'a> {
         self.graph.create(name, desc)
     }
 
-    pub fn read_from_board<ResourceType>(
+    pub fn export<ResourceType: 'static>(
+        &mut self,
+        name: &str,
+        handle: ResourceNodeHandle<ResourceType>,
+    ) -> Result<(), RendererError> {
+        self.graph.export(name, handle)
+    }
+
+    pub fn update_board<ResourceType: 'static>(
+        &mut self,
+        name: &str,
+        handle: ResourceNodeHandle<ResourceType>,
+    ) -> Result<(), RendererError> {
+        self.graph.update_board(name, handle)
+    }
+
+    pub fn read_from_board<ResourceType: 'static>(
         &mut self,
         name: &str,
-    ) -> Option<ResourceNodeRef<ResourceType, GpuRead>> {
+    ) -> Result<ResourceNodeRef<ResourceType, GpuRead>, RendererError> {
         self.pass_node
             .as_mut()
             .unwrap()
@@ -0,0 +1,163 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::gfx_base::{QuerySet, QuerySetInfo, QueryType, device::Device};
+
+///同时等待回读的最大帧数
+const MAX_FRAMES_IN_FLIGHT: usize = 3;
+
+///渲染节点的耗时
+#[derive(Debug, Clone)]
+pub struct PassTiming {
+    pub name: String,
+    ///录制命令的CPU耗时
+    pub cpu_time: Duration,
+    ///所在硬件渲染通道的GPU耗时,合并的渲染节点共享同一个时间
+    pub gpu_time: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FrameTimings {
+    pub passes: Vec<PassTiming>,
+}
+
+struct ProfilerFrame {
+    query_set: Arc<QuerySet>,
+    ///等待GPU结果的计时,以及每个渲染节点所在硬件渲染通道的索引
+    pending: Option<(FrameTimings, Vec<usize>)>,
+}
+
+impl ProfilerFrame {
+    fn is_free(&self) -> bool {
+        self.pending.is_none() && !self.query_set.is_pending()
+    }
+}
+
+///使用时间戳查询统计每个硬件渲染通道的耗时,结果在之后的帧中异步读取
+#[derive(Default)]
+pub struct GpuProfiler {
+    enable: bool,
+    frames: Vec<ProfilerFrame>,
+    current: Option<usize>,
+    timings: Option<FrameTimings>,
+}
+
+impl GpuProfiler {
+    pub fn set_enable(&mut self, enable: bool) {
+        self.enable = enable;
+
+        if !enable {
+            self.frames.clear();
+            self.current = None;
+            self.timings = None;
+        }
+    }
+
+    pub fn is_enable(&self) -> bool {
+        self.enable
+    }
+
+    ///最近一帧已完成的计时
+    pub fn timings(&self) -> Option<&FrameTimings> {
+        self.timings.as_ref()
+    }
+
+    fn poll(&mut self, timestamp_period: f32) {
+        for frame in self.frames.iter_mut() {
+            if frame.pending.is_none() {
+                continue;
+            }
+
+            if let Some(data) = frame.query_set.try_read() {
+                let (mut timings, device_pass_indices) = frame.pending.take().unwrap();
+
+                for (pass, index) in timings.passes.iter_mut().zip(device_pass_indices) {
+                    if let (Some(begin), Some(end)) = (data.get(index * 2), data.get(index * 2 + 1))
+                    {
+                        let nanos = end.saturating_sub(*begin) as f64 * timestamp_period as f64;
+                        pass.gpu_time = Some(Duration::from_nanos(nanos as u64));
+                    }
+                }
+
+                self.timings = Some(timings);
+            } else if !frame.query_set.is_pending() {
+                //查询集未被解析,只发布CPU耗时
+                if let Some((timings, _)) = frame.pending.take() {
+                    self.timings = Some(timings);
+                }
+            }
+        }
+    }
+
+    ///开始一帧,返回本帧用于时间戳查询的查询集
+    pub fn begin_frame(
+        &mut self,
+        device: &Device,
+        device_pass_count: usize,
+    ) -> Option<Arc<QuerySet>> {
+        self.current = None;
+
+        if !self.enable {
+            return None;
+        }
+
+        self.poll(device.timestamp_period());
+
+        if device_pass_count == 0 || !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+
+        let count = device_pass_count as u32 * 2;
+
+        let create_query_set = || {
+            Arc::new(device.create_query_set(QuerySetInfo {
+                label: Some("gpu_profiler".into()),
+                ty: QueryType::Timestamp,
+                count,
+            }))
+        };
+
+        let index = match self.frames.iter().position(|frame| frame.is_free()) {
+            Some(index) => {
+                let frame = &mut self.frames[index];
+
+                if frame.query_set.get_desc().count < count {
+                    frame.query_set = create_query_set();
+                }
+
+                index
+            }
+            None if self.frames.len() < MAX_FRAMES_IN_FLIGHT => {
+                self.frames.push(ProfilerFrame {
+                    query_set: create_query_set(),
+                    pending: None,
+                });
+
+                self.frames.len() - 1
+            }
+            //所有查询集都在等待回读,本帧不统计GPU耗时
+            None => return None,
+        };
+
+        self.current = Some(index);
+
+        Some(self.frames[index].query_set.clone())
+    }
+
+    ///结束一帧,有GPU计时的结果等待回读,否则直接发布
+    pub fn end_frame(&mut self, timings: FrameTimings, device_pass_indices: Vec<usize>) {
+        if !self.enable {
+            return;
+        }
+
+        match self.current.take() {
+            Some(index) => {
+                let frame = &mut self.frames[index];
+                frame.query_set.request_read();
+                frame.pending = Some((timings, device_pass_indices));
+            }
+            None => {
+                self.timings = Some(timings);
+            }
+        }
+    }
+}
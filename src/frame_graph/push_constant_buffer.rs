@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+
+use tracing::error;
+
+use crate::gfx_base::{
+    BindGroup, BindGroupEntry, BindGroupInfo, BindGroupLayout, BindingResource,
+    buffer::{Buffer, BufferInfo},
+    device::Device,
+};
+
+///每次写入占用的槽位大小,满足所有设备的uniform缓冲区动态偏移对齐
+pub const PUSH_CONSTANT_SLOT_SIZE: u64 = 256;
+
+const INITIAL_SLOT_COUNT: u64 = 64;
+
+///设备不支持推送常量时承载推送常量的uniform缓冲区,跨帧保留
+///
+///每次写入使用新的槽位并通过动态偏移绑定,同一帧内不会覆盖已录制的绘制使用的数据。
+///写入通过队列完成,下一帧从头复用槽位时前一帧的命令已经先提交。
+#[derive(Default)]
+pub struct PushConstantBuffer {
+    buffer: Option<Buffer>,
+    capacity: u64,
+    offset: u64,
+    bind_groups: HashMap<BindGroupLayout, BindGroup>,
+}
+
+impl PushConstantBuffer {
+    ///每帧开始时调用
+    pub fn reset(&mut self) {
+        self.offset = 0;
+        self.bind_groups.clear();
+    }
+
+    ///写入数据,返回绑定到回退组的绑定组和动态偏移
+    pub fn write(
+        &mut self,
+        device: &Device,
+        layout: &BindGroupLayout,
+        data: &[u8],
+    ) -> Option<(&BindGroup, wgpu::DynamicOffset)> {
+        if data.len() as u64 > PUSH_CONSTANT_SLOT_SIZE {
+            error!(
+                "push constants of {} bytes exceed the fallback slot size {}",
+                data.len(),
+                PUSH_CONSTANT_SLOT_SIZE
+            );
+            return None;
+        }
+
+        if self.buffer.is_none() || self.offset + PUSH_CONSTANT_SLOT_SIZE > self.capacity {
+            self.grow(device);
+        }
+
+        let buffer = self.buffer.as_ref()?;
+        let offset = self.offset;
+        self.offset += PUSH_CONSTANT_SLOT_SIZE;
+
+        //写入整个槽位,着色器读取未设置的部分时得到0
+        let mut contents = data.to_vec();
+        contents.resize(PUSH_CONSTANT_SLOT_SIZE as usize, 0);
+        device.write_buffer(buffer, offset, &contents);
+
+        let bind_group = self.bind_groups.entry(layout.clone()).or_insert_with(|| {
+            device.create_bind_group(BindGroupInfo {
+                label: Some("push_constant_fallback".into()),
+                layout: layout.clone(),
+                entries: vec![BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::BufferRange {
+                        buffer: buffer.clone(),
+                        offset: 0,
+                        size: wgpu::BufferSize::new(PUSH_CONSTANT_SLOT_SIZE).unwrap(),
+                    },
+                }],
+            })
+        });
+
+        Some((bind_group, offset as wgpu::DynamicOffset))
+    }
+
+    ///容量不足时换用更大的缓冲区,已录制的绘制仍然引用旧缓冲区
+    fn grow(&mut self, device: &Device) {
+        let capacity = (self.capacity * 2).max(PUSH_CONSTANT_SLOT_SIZE * INITIAL_SLOT_COUNT);
+
+        self.buffer = Some(device.create_buffer(BufferInfo {
+            label: Some("push_constant_fallback".into()),
+            size: capacity,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        }));
+        self.capacity = capacity;
+        self.offset = 0;
+        self.bind_groups.clear();
+    }
+}
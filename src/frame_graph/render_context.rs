@@ -4,15 +4,25 @@ use crate::{
     error::{RendererError, Result},
     frame_graph::Resource,
     gfx_base::{
-        BindGroupRef,
+        BindGroupLayout, BindGroupRef, QuerySet,
         buffer::Buffer,
         command_buffer::CommandBuffer,
         device::Device,
-        pipeline::{CachedRenderPipelineId, PipelineCache},
+        pipeline::{
+            CachedComputePipelineId, CachedRenderPipelineId, PUSH_CONSTANT_FALLBACK_GROUP,
+            PipelineCache,
+        },
     },
 };
 
-use super::{GpuRead, ResourceNodeRef, ResourceTable, TransientResourceCache};
+use super::{
+    GpuRead, GpuWrite, PushConstantBuffer, ResourceNodeRef, ResourceTable, TransientResourceCache,
+};
+
+///间接绘制参数的字节大小
+const DRAW_INDIRECT_STRIDE: u64 = std::mem::size_of::<wgpu::util::DrawIndirectArgs>() as u64;
+const DRAW_INDEXED_INDIRECT_STRIDE: u64 =
+    std::mem::size_of::<wgpu::util::DrawIndexedIndirectArgs>() as u64;
 
 pub type DynRenderFn = dyn FnOnce(&mut RenderContext) -> Result<(), RendererError>;
 
@@ -24,6 +34,11 @@ pub struct RenderContext<'a> {
     pub pipeline_cache: &'a PipelineCache,
     cb: Option<CommandBuffer>,
     pub transient_resource_cache: &'a mut TransientResourceCache,
+    ///当前管线的推送常量回退布局,设备支持推送常量时为空
+    push_constant_fallback_layout: Option<BindGroupLayout>,
+    ///当前管线已设置的推送常量,切换管线时清空
+    push_constant_data: Vec<u8>,
+    push_constant_buffer: &'a mut PushConstantBuffer,
 }
 
 impl<'a> RenderContext<'a> {
@@ -35,28 +50,176 @@ impl<'a> RenderContext<'a> {
         self.cb.take()
     }
 
+    pub fn cb_mut(&mut self) -> Option<&mut CommandBuffer> {
+        self.cb.as_mut()
+    }
+
+    pub fn push_debug_group(&mut self, label: &str) {
+        if let Some(cb) = self.cb.as_mut() {
+            cb.push_debug_group(label);
+        }
+    }
+
+    pub fn pop_debug_group(&mut self) {
+        if let Some(cb) = self.cb.as_mut() {
+            cb.pop_debug_group();
+        }
+    }
+
+    pub fn insert_debug_marker(&mut self, label: &str) {
+        if let Some(cb) = self.cb.as_mut() {
+            cb.insert_debug_marker(label);
+        }
+    }
+
+    ///遮挡查询写入当前渲染节点设置的遮挡查询集
+    pub fn begin_occlusion_query(&mut self, query_index: u32) {
+        if let Some(cb) = self.cb.as_mut() {
+            cb.begin_occlusion_query(query_index);
+        }
+    }
+
+    pub fn end_occlusion_query(&mut self) {
+        if let Some(cb) = self.cb.as_mut() {
+            cb.end_occlusion_query();
+        }
+    }
+
+    ///设备不支持PIPELINE_STATISTICS_QUERY时忽略
+    pub fn begin_pipeline_statistics_query(
+        &mut self,
+        handle: &ResourceNodeRef<QuerySet, GpuWrite>,
+        query_index: u32,
+    ) {
+        if !self
+            .device
+            .features()
+            .contains(wgpu::Features::PIPELINE_STATISTICS_QUERY)
+        {
+            return;
+        }
+
+        if let (Some(query_set), Some(cb)) = (
+            self.resource_table
+                .get_resource::<QuerySet>(&handle.resource_handle()),
+            self.cb.as_mut(),
+        ) {
+            cb.begin_pipeline_statistics_query(query_set, query_index);
+        }
+    }
+
+    pub fn end_pipeline_statistics_query(&mut self) {
+        if !self
+            .device
+            .features()
+            .contains(wgpu::Features::PIPELINE_STATISTICS_QUERY)
+        {
+            return;
+        }
+
+        if let Some(cb) = self.cb.as_mut() {
+            cb.end_pipeline_statistics_query();
+        }
+    }
+
+    ///解析查询结果,帧图提交后开始回读,之后的帧中通过QuerySet::try_read获取
+    ///只有导入的查询集会回读,临时查询集的结果在帧结束时丢弃
+    pub fn resolve_query_set(&mut self, handle: &ResourceNodeRef<QuerySet, GpuWrite>, count: u32) {
+        if let (Some(query_set), Some(cb)) = (
+            self.resource_table
+                .get_resource::<QuerySet>(&handle.resource_handle()),
+            self.cb.as_mut(),
+        ) {
+            cb.resolve_query_set(query_set, count);
+        }
+    }
+
     pub fn set_bind_group(&mut self, index: u32, bind_group: &BindGroupRef) {
         if let Some(cb) = self.cb.as_mut() {
             let info = bind_group.get_info(self.device, &self.resource_table);
             let bind_group = self.device.create_bind_group(info);
 
-            cb.set_bind_group(index, &bind_group);
+            cb.set_bind_group(index, &bind_group, &[]);
         }
     }
 
     pub fn set_render_pipeline(&mut self, id: &CachedRenderPipelineId) {
-        if let Some(pipeline) = self.pipeline_cache.get_render_pipeline(id) {
+        if let (Some(pipeline), Some(cb)) = (
+            self.pipeline_cache.get_render_pipeline(id),
+            self.cb.as_mut(),
+        ) {
+            cb.set_render_pipeline(pipeline);
+        }
+
+        self.push_constant_data.clear();
+        self.push_constant_fallback_layout = self
+            .pipeline_cache
+            .get_render_pipeline_bind_group_layout(id, PUSH_CONSTANT_FALLBACK_GROUP as usize)
+            .filter(|_| !self.supports_push_constants())
+            .cloned();
+    }
+
+    pub fn set_compute_pipeline(&mut self, id: &CachedComputePipelineId) {
+        if let (Some(pipeline), Some(cb)) = (
+            self.pipeline_cache.get_compute_pipeline(id),
+            self.cb.as_mut(),
+        ) {
+            cb.set_compute_pipeline(pipeline);
+        }
+
+        self.push_constant_data.clear();
+        self.push_constant_fallback_layout = self
+            .pipeline_cache
+            .get_compute_pipeline_bind_group_layout(id, PUSH_CONSTANT_FALLBACK_GROUP as usize)
+            .filter(|_| !self.supports_push_constants())
+            .cloned();
+    }
+
+    fn supports_push_constants(&self) -> bool {
+        self.has_feature(wgpu::Features::PUSH_CONSTANTS)
+    }
+
+    ///设备不支持推送常量时写入uniform缓冲区,绑定到PUSH_CONSTANT_FALLBACK_GROUP
+    pub fn set_push_constants(&mut self, stages: wgpu::ShaderStages, offset: u32, data: &[u8]) {
+        if self.supports_push_constants() {
             if let Some(cb) = self.cb.as_mut() {
-                cb.set_render_pipeline(pipeline);
+                cb.set_push_constants(stages, offset, data);
             }
+            return;
+        }
+
+        let Some(layout) = self.push_constant_fallback_layout.clone() else {
+            return;
+        };
+
+        let start = offset as usize;
+        let end = start + data.len();
+        if self.push_constant_data.len() < end {
+            self.push_constant_data.resize(end, 0);
+        }
+        self.push_constant_data[start..end].copy_from_slice(data);
+
+        if let (Some((bind_group, dynamic_offset)), Some(cb)) = (
+            self.push_constant_buffer
+                .write(self.device, &layout, &self.push_constant_data),
+            self.cb.as_mut(),
+        ) {
+            cb.set_bind_group(PUSH_CONSTANT_FALLBACK_GROUP, bind_group, &[dynamic_offset]);
+        }
+    }
+
+    pub fn dispatch_workgroups(&mut self, x: u32, y: u32, z: u32) {
+        if let Some(cb) = self.cb.as_mut() {
+            cb.dispatch_workgroups(x, y, z);
         }
     }
 
     pub fn set_vertex_buffer(&mut self, slot: u32, handle: ResourceNodeRef<Buffer, GpuRead>) {
-        if let Some(buffer) = self.resource_table.get_resource(&handle.resource_handle()) {
-            if let Some(cb) = self.cb.as_mut() {
-                cb.set_vertex_buffer(slot, buffer);
-            }
+        if let (Some(buffer), Some(cb)) = (
+            self.resource_table.get_resource(&handle.resource_handle()),
+            self.cb.as_mut(),
+        ) {
+            cb.set_vertex_buffer(slot, buffer);
         }
     }
 
@@ -65,10 +228,11 @@ impl<'a> RenderContext<'a> {
         handle: ResourceNodeRef<Buffer, GpuRead>,
         index_format: wgpu::IndexFormat,
     ) {
-        if let Some(buffer) = self.resource_table.get_resource(&handle.resource_handle()) {
-            if let Some(cb) = self.cb.as_mut() {
-                cb.set_index_buffer(buffer, index_format);
-            }
+        if let (Some(buffer), Some(cb)) = (
+            self.resource_table.get_resource(&handle.resource_handle()),
+            self.cb.as_mut(),
+        ) {
+            cb.set_index_buffer(buffer, index_format);
         }
     }
 
@@ -84,6 +248,150 @@ impl<'a> RenderContext<'a> {
         }
     }
 
+    pub fn draw_indirect(
+        &mut self,
+        handle: ResourceNodeRef<Buffer, GpuRead>,
+        indirect_offset: u64,
+    ) {
+        if let (Some(buffer), Some(cb)) = (
+            self.resource_table.get_resource(&handle.resource_handle()),
+            self.cb.as_mut(),
+        ) {
+            cb.draw_indirect(buffer, indirect_offset);
+        }
+    }
+
+    pub fn draw_indexed_indirect(
+        &mut self,
+        handle: ResourceNodeRef<Buffer, GpuRead>,
+        indirect_offset: u64,
+    ) {
+        if let (Some(buffer), Some(cb)) = (
+            self.resource_table.get_resource(&handle.resource_handle()),
+            self.cb.as_mut(),
+        ) {
+            cb.draw_indexed_indirect(buffer, indirect_offset);
+        }
+    }
+
+    ///设备不支持MULTI_DRAW_INDIRECT时逐个绘制
+    pub fn multi_draw_indirect(
+        &mut self,
+        handle: ResourceNodeRef<Buffer, GpuRead>,
+        indirect_offset: u64,
+        count: u32,
+    ) {
+        let supported = self.has_feature(wgpu::Features::MULTI_DRAW_INDIRECT);
+
+        if let (Some(buffer), Some(cb)) = (
+            self.resource_table.get_resource(&handle.resource_handle()),
+            self.cb.as_mut(),
+        ) {
+            if supported {
+                cb.multi_draw_indirect(buffer, indirect_offset, count);
+            } else {
+                for index in 0..count as u64 {
+                    cb.draw_indirect(buffer, indirect_offset + index * DRAW_INDIRECT_STRIDE);
+                }
+            }
+        }
+    }
+
+    pub fn multi_draw_indexed_indirect(
+        &mut self,
+        handle: ResourceNodeRef<Buffer, GpuRead>,
+        indirect_offset: u64,
+        count: u32,
+    ) {
+        let supported = self.has_feature(wgpu::Features::MULTI_DRAW_INDIRECT);
+
+        if let (Some(buffer), Some(cb)) = (
+            self.resource_table.get_resource(&handle.resource_handle()),
+            self.cb.as_mut(),
+        ) {
+            if supported {
+                cb.multi_draw_indexed_indirect(buffer, indirect_offset, count);
+            } else {
+                for index in 0..count as u64 {
+                    cb.draw_indexed_indirect(
+                        buffer,
+                        indirect_offset + index * DRAW_INDEXED_INDIRECT_STRIDE,
+                    );
+                }
+            }
+        }
+    }
+
+    ///设备不支持MULTI_DRAW_INDIRECT_COUNT时返回错误,不会忽略数量缓冲区按max_count绘制。
+    ///调用者需要自行回退,例如由剔除通道将多余参数的实例数写为0后调用multi_draw_indirect
+    pub fn multi_draw_indirect_count(
+        &mut self,
+        handle: ResourceNodeRef<Buffer, GpuRead>,
+        indirect_offset: u64,
+        count_handle: ResourceNodeRef<Buffer, GpuRead>,
+        count_offset: u64,
+        max_count: u32,
+    ) -> Result<(), RendererError> {
+        if !self.has_feature(wgpu::Features::MULTI_DRAW_INDIRECT_COUNT) {
+            return Err(RendererError::UnsupportedFeature(
+                wgpu::Features::MULTI_DRAW_INDIRECT_COUNT,
+            ));
+        }
+
+        if let (Some(buffer), Some(count_buffer), Some(cb)) = (
+            self.resource_table.get_resource(&handle.resource_handle()),
+            self.resource_table
+                .get_resource(&count_handle.resource_handle()),
+            self.cb.as_mut(),
+        ) {
+            cb.multi_draw_indirect_count(
+                buffer,
+                indirect_offset,
+                count_buffer,
+                count_offset,
+                max_count,
+            );
+        }
+
+        Ok(())
+    }
+
+    pub fn multi_draw_indexed_indirect_count(
+        &mut self,
+        handle: ResourceNodeRef<Buffer, GpuRead>,
+        indirect_offset: u64,
+        count_handle: ResourceNodeRef<Buffer, GpuRead>,
+        count_offset: u64,
+        max_count: u32,
+    ) -> Result<(), RendererError> {
+        if !self.has_feature(wgpu::Features::MULTI_DRAW_INDIRECT_COUNT) {
+            return Err(RendererError::UnsupportedFeature(
+                wgpu::Features::MULTI_DRAW_INDIRECT_COUNT,
+            ));
+        }
+
+        if let (Some(buffer), Some(count_buffer), Some(cb)) = (
+            self.resource_table.get_resource(&handle.resource_handle()),
+            self.resource_table
+                .get_resource(&count_handle.resource_handle()),
+            self.cb.as_mut(),
+        ) {
+            cb.multi_draw_indexed_indirect_count(
+                buffer,
+                indirect_offset,
+                count_buffer,
+                count_offset,
+                max_count,
+            );
+        }
+
+        Ok(())
+    }
+
+    fn has_feature(&self, feature: wgpu::Features) -> bool {
+        self.device.features().contains(feature)
+    }
+
     pub fn device(&self) -> &Device {
         self.device
     }
@@ -92,6 +400,7 @@ impl<'a> RenderContext<'a> {
         device: &'a Arc<Device>,
         pipeline_cache: &'a PipelineCache,
         transient_resource_cache: &'a mut TransientResourceCache,
+        push_constant_buffer: &'a mut PushConstantBuffer,
     ) -> Self {
         Self {
             resource_table: Default::default(),
@@ -99,6 +408,9 @@ impl<'a> RenderContext<'a> {
             pipeline_cache,
             cb: None,
             transient_resource_cache,
+            push_constant_fallback_layout: None,
+            push_constant_data: vec![],
+            push_constant_buffer,
         }
     }
 
@@ -1,11 +1,12 @@
 mod buffer;
+mod query_set;
 mod texture;
 mod texture_view;
 
 use std::{hash::Hash, sync::Arc};
 
 use crate::gfx_base::{
-    Texture, TextureInfo,
+    QuerySet, QuerySetInfo, Texture, TextureInfo,
     buffer::{Buffer, BufferInfo},
     handle::TypeHandle,
     texture_view::{TextureView, TextureViewInfo},
@@ -18,6 +19,41 @@ pub enum AnyResourceDescriptor {
     Texture(TextureInfo),
     Buffer(BufferInfo),
     TextureView(TextureViewInfo),
+    QuerySet(QuerySetInfo),
+}
+
+impl AnyResourceDescriptor {
+    pub fn with_label(self, label: &str) -> Self {
+        match self {
+            AnyResourceDescriptor::Texture(mut desc) => {
+                desc.label = Some(label.to_owned().into());
+                AnyResourceDescriptor::Texture(desc)
+            }
+            AnyResourceDescriptor::Buffer(mut desc) => {
+                desc.label = Some(label.to_owned().into());
+                AnyResourceDescriptor::Buffer(desc)
+            }
+            AnyResourceDescriptor::TextureView(desc) => AnyResourceDescriptor::TextureView(desc),
+            AnyResourceDescriptor::QuerySet(mut desc) => {
+                desc.label = Some(label.to_owned().into());
+                AnyResourceDescriptor::QuerySet(desc)
+            }
+        }
+    }
+
+    ///去掉名称,名称不同的资源可以复用,复用时不会更新资源的调试标签
+    pub fn without_label(&self) -> Self {
+        let mut desc = self.clone();
+
+        match &mut desc {
+            AnyResourceDescriptor::Texture(desc) => desc.label = None,
+            AnyResourceDescriptor::Buffer(desc) => desc.label = None,
+            AnyResourceDescriptor::TextureView(_) => {}
+            AnyResourceDescriptor::QuerySet(desc) => desc.label = None,
+        }
+
+        desc
+    }
 }
 
 pub enum AnyResource {
@@ -26,6 +62,8 @@ pub enum AnyResource {
     ImportedTexture(Arc<Texture>),
     ImportedBuffer(Arc<Buffer>),
     ImportedTextureView(Arc<TextureView>),
+    OwnedQuerySet(QuerySet),
+    ImportedQuerySet(Arc<QuerySet>),
 }
 
 pub trait Resource: 'static {
@@ -64,6 +102,27 @@ pub enum ImportedVirtualResource {
     Texture(Arc<Texture>),
     Buffer(Arc<Buffer>),
     TextureView(Arc<TextureView>),
+    QuerySet(Arc<QuerySet>),
+}
+
+impl ImportedVirtualResource {
+    pub fn ptr_eq(&self, other: &ImportedVirtualResource) -> bool {
+        match (self, other) {
+            (ImportedVirtualResource::Texture(a), ImportedVirtualResource::Texture(b)) => {
+                Arc::ptr_eq(a, b)
+            }
+            (ImportedVirtualResource::Buffer(a), ImportedVirtualResource::Buffer(b)) => {
+                Arc::ptr_eq(a, b)
+            }
+            (ImportedVirtualResource::TextureView(a), ImportedVirtualResource::TextureView(b)) => {
+                Arc::ptr_eq(a, b)
+            }
+            (ImportedVirtualResource::QuerySet(a), ImportedVirtualResource::QuerySet(b)) => {
+                Arc::ptr_eq(a, b)
+            }
+            _ => false,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -140,6 +199,11 @@ impl ResourceInfo {
         self.version += 1
     }
 
+    ///放弃构建渲染节点时恢复到写入前的版本
+    pub(crate) fn set_version(&mut self, version: u32) {
+        self.version = version
+    }
+
     pub fn update_lifetime(&mut self, handle: TypeHandle<PassNode>) {
         if self.first_pass_node_handle.is_none() {
             self.first_pass_node_handle = Some(handle);
@@ -0,0 +1,44 @@
+use std::sync::Arc;
+
+use crate::{
+    frame_graph::{
+        AnyResource, AnyResourceDescriptor, ImportToFrameGraph, Resource, ResourceDescriptor,
+    },
+    gfx_base::query_set::{QuerySet, QuerySetInfo},
+};
+
+use super::ImportedVirtualResource;
+
+impl ResourceDescriptor for QuerySetInfo {
+    type Resource = QuerySet;
+}
+
+impl From<QuerySetInfo> for AnyResourceDescriptor {
+    fn from(value: QuerySetInfo) -> Self {
+        AnyResourceDescriptor::QuerySet(value)
+    }
+}
+
+impl ImportToFrameGraph for QuerySet {
+    fn import(self: Arc<Self>) -> ImportedVirtualResource {
+        ImportedVirtualResource::QuerySet(self)
+    }
+}
+
+impl Resource for QuerySet {
+    type Descriptor = QuerySetInfo;
+
+    fn borrow_resource(res: &AnyResource) -> &Self {
+        match &res {
+            AnyResource::OwnedQuerySet(res) => res,
+            AnyResource::ImportedQuerySet(res) => res,
+            _ => {
+                unimplemented!()
+            }
+        }
+    }
+
+    fn get_desc(&self) -> &Self::Descriptor {
+        self.get_desc()
+    }
+}
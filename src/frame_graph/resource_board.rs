@@ -1,18 +1,261 @@
-use std::collections::HashMap;
+use std::{
+    any::{TypeId, type_name},
+    collections::HashMap,
+};
 
-use super::RawResourceNodeHandle;
+use crate::error::RendererError;
 
-#[derive(Default)]
+use super::{RawResourceNodeHandle, ResourceNodeHandle};
+
+///资源面板中的条目,记录资源节点及其资源类型
+#[derive(Clone)]
+pub struct ResourceBoardEntry {
+    type_id: TypeId,
+    type_name: &'static str,
+    handle: RawResourceNodeHandle,
+}
+
+impl ResourceBoardEntry {
+    pub fn new<ResourceType: 'static>(handle: &ResourceNodeHandle<ResourceType>) -> Self {
+        ResourceBoardEntry {
+            type_id: TypeId::of::<ResourceType>(),
+            type_name: type_name::<ResourceType>(),
+            handle: handle.raw(),
+        }
+    }
+
+    pub fn handle(&self) -> &RawResourceNodeHandle {
+        &self.handle
+    }
+
+    pub fn typed<ResourceType: 'static>(
+        &self,
+        name: &str,
+    ) -> Result<ResourceNodeHandle<ResourceType>, RendererError> {
+        if self.type_id != TypeId::of::<ResourceType>() {
+            return Err(RendererError::ResourceBoardTypeMismatch {
+                name: name.to_owned(),
+                expected: type_name::<ResourceType>().to_owned(),
+                found: self.type_name.to_owned(),
+            });
+        }
+
+        Ok(self.handle.clone().into())
+    }
+}
+
+#[derive(Default, Clone)]
 pub struct ResourceBoard {
-    resources: HashMap<String, RawResourceNodeHandle>,
+    resources: HashMap<String, ResourceBoardEntry>,
+    ///当前子帧图的命名空间
+    scopes: Vec<String>,
 }
 
 impl ResourceBoard {
-    pub fn put(&mut self, name: &str, handle: RawResourceNodeHandle) {
-        self.resources.insert(name.to_owned(), handle);
+    pub fn push_scope(&mut self, scope: &str) {
+        self.scopes.push(scope.to_owned());
+    }
+
+    pub fn pop_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    pub(crate) fn scoped_name(&self, name: &str) -> String {
+        if self.scopes.is_empty() {
+            name.to_owned()
+        } else {
+            format!("{}/{}", self.scopes.join("/"), name)
+        }
+    }
+
+    ///资源写入新版本后,指向该资源的条目同步到最新的资源节点
+    pub fn update_resource_node(&mut self, handle: RawResourceNodeHandle) {
+        for entry in self.resources.values_mut() {
+            if entry.handle.resource_handle() == handle.resource_handle() {
+                entry.handle = handle.clone();
+            }
+        }
+    }
+
+    ///名称已被其他资源占用时返回错误,同一资源则指向新的资源节点
+    pub fn put_entry(
+        &mut self,
+        name: &str,
+        entry: ResourceBoardEntry,
+    ) -> Result<(), RendererError> {
+        if let Some(exist) = self.get_entry(name) {
+            if exist.type_id != entry.type_id {
+                return Err(RendererError::ResourceBoardTypeMismatch {
+                    name: name.to_owned(),
+                    expected: entry.type_name.to_owned(),
+                    found: exist.type_name.to_owned(),
+                });
+            }
+
+            if exist.handle.resource_handle() != entry.handle.resource_handle() {
+                return Err(RendererError::ResourceBoardConflict {
+                    name: name.to_owned(),
+                });
+            }
+        }
+
+        self.resources.insert(self.scoped_name(name), entry);
+
+        Ok(())
+    }
+
+    pub fn get_entry(&self, name: &str) -> Option<&ResourceBoardEntry> {
+        self.resources.get(&self.scoped_name(name))
     }
 
-    pub fn get(&self, name: &str) -> Option<&RawResourceNodeHandle> {
-        self.resources.get(name)
+    pub fn put<ResourceType: 'static>(
+        &mut self,
+        name: &str,
+        handle: ResourceNodeHandle<ResourceType>,
+    ) -> Result<(), RendererError> {
+        self.put_entry(name, ResourceBoardEntry::new(&handle))
+    }
+
+    ///将已有条目指向同一资源的最新版本
+    pub fn update<ResourceType: 'static>(
+        &mut self,
+        name: &str,
+        handle: ResourceNodeHandle<ResourceType>,
+    ) -> Result<(), RendererError> {
+        if self.get_entry(name).is_none() {
+            return Err(RendererError::ResourceBoardNotFound {
+                name: name.to_owned(),
+            });
+        }
+
+        self.put(name, handle)
+    }
+
+    pub fn get<ResourceType: 'static>(
+        &self,
+        name: &str,
+    ) -> Result<ResourceNodeHandle<ResourceType>, RendererError> {
+        self.get_entry(name)
+            .ok_or_else(|| RendererError::ResourceBoardNotFound {
+                name: name.to_owned(),
+            })?
+            .typed(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        error::RendererError,
+        frame_graph::ResourceNodeHandle,
+        gfx_base::{Buffer, Texture, handle::TypeHandle},
+    };
+
+    use super::ResourceBoard;
+
+    fn buffer_handle(resource_node: usize, resource: usize) -> ResourceNodeHandle<Buffer> {
+        ResourceNodeHandle::new(TypeHandle::new(resource_node), TypeHandle::new(resource))
+    }
+
+    #[test]
+    fn put_and_get() {
+        let mut board = ResourceBoard::default();
+        board.put("buffer", buffer_handle(0, 0)).unwrap();
+
+        let handle = board.get::<Buffer>("buffer").unwrap();
+        assert_eq!(handle.resource_node_handle().index(), 0);
+        assert_eq!(handle.resource_handle().index(), 0);
+    }
+
+    #[test]
+    fn get_missing_entry() {
+        let board = ResourceBoard::default();
+
+        assert!(matches!(
+            board.get::<Buffer>("buffer"),
+            Err(RendererError::ResourceBoardNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn get_with_wrong_type() {
+        let mut board = ResourceBoard::default();
+        board.put("buffer", buffer_handle(0, 0)).unwrap();
+
+        assert!(matches!(
+            board.get::<Texture>("buffer"),
+            Err(RendererError::ResourceBoardTypeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn put_another_resource_with_same_name() {
+        let mut board = ResourceBoard::default();
+        board.put("buffer", buffer_handle(0, 0)).unwrap();
+
+        assert!(matches!(
+            board.put("buffer", buffer_handle(1, 1)),
+            Err(RendererError::ResourceBoardConflict { .. })
+        ));
+
+        //同一资源的新版本可以覆盖
+        board.put("buffer", buffer_handle(2, 0)).unwrap();
+        assert_eq!(
+            board
+                .get::<Buffer>("buffer")
+                .unwrap()
+                .resource_node_handle()
+                .index(),
+            2
+        );
+    }
+
+    #[test]
+    fn scoped_names() {
+        let mut board = ResourceBoard::default();
+        board.put("buffer", buffer_handle(0, 0)).unwrap();
+
+        board.push_scope("a");
+        board.put("buffer", buffer_handle(1, 1)).unwrap();
+        assert_eq!(
+            board
+                .get::<Buffer>("buffer")
+                .unwrap()
+                .resource_handle()
+                .index(),
+            1
+        );
+        board.pop_scope();
+
+        assert_eq!(
+            board
+                .get::<Buffer>("buffer")
+                .unwrap()
+                .resource_handle()
+                .index(),
+            0
+        );
+        assert_eq!(
+            board
+                .get::<Buffer>("a/buffer")
+                .unwrap()
+                .resource_handle()
+                .index(),
+            1
+        );
+    }
+
+    #[test]
+    fn update_resource_node() {
+        let mut board = ResourceBoard::default();
+        board.put("a", buffer_handle(0, 0)).unwrap();
+        board.put("b", buffer_handle(1, 1)).unwrap();
+
+        board.update_resource_node(buffer_handle(2, 0).raw());
+
+        let a = board.get::<Buffer>("a").unwrap();
+        let b = board.get::<Buffer>("b").unwrap();
+        assert_eq!(a.resource_node_handle().index(), 2);
+        assert_eq!(b.resource_node_handle().index(), 1);
     }
 }
@@ -20,6 +20,10 @@ impl<ResourceType, ViewType> Clone for ResourceNodeRef<ResourceType, ViewType> {
 }
 
 impl<ResourceType, ViewType> ResourceNodeRef<ResourceType, ViewType> {
+    pub fn handle(&self) -> ResourceNodeHandle<ResourceType> {
+        self.handle.clone()
+    }
+
     pub fn resource_node_handle(&self) -> TypeHandle<ResourceNode> {
         self.handle.resource_node_handle
     }
@@ -1,7 +1,10 @@
 use std::collections::HashMap;
 
 use crate::{
-    frame_graph::{AnyResource, ImportedVirtualResource, Resource, VirtualResource},
+    error::RendererError,
+    frame_graph::{
+        AnyResource, AnyResourceDescriptor, ImportedVirtualResource, Resource, VirtualResource,
+    },
     gfx_base::{device::Device, handle::TypeHandle},
 };
 
@@ -23,12 +26,13 @@ impl ResourceTable {
             .map(|any| ResourceType::borrow_resource(any))
     }
 
+    ///设备不支持临时查询集的查询类型时返回错误
     pub fn request_resource(
         &mut self,
         resource: &VirtualResource,
         device: &Device,
         transient_resource_cache: &mut TransientResourceCache,
-    ) {
+    ) -> Result<(), RendererError> {
         let handle = resource.info.handle;
         let resource = match &resource.state {
             ResourceState::Imported(state) => match &state.resource {
@@ -41,9 +45,20 @@ impl ResourceTable {
                 ImportedVirtualResource::TextureView(resource) => {
                     AnyResource::ImportedTextureView(resource.clone())
                 }
+                ImportedVirtualResource::QuerySet(resource) => {
+                    AnyResource::ImportedQuerySet(resource.clone())
+                }
             },
             ResourceState::Setup(desc) => {
-                let desc = desc.clone();
+                if let AnyResourceDescriptor::QuerySet(info) = desc {
+                    let required_features = info.ty.required_features();
+
+                    if !device.features().contains(required_features) {
+                        return Err(RendererError::UnsupportedFeature(required_features));
+                    }
+                }
+
+                let desc = desc.clone().with_label(&resource.info.name);
                 transient_resource_cache
                     .get_resource(&desc)
                     .unwrap_or_else(|| device.create(desc))
@@ -51,6 +66,8 @@ impl ResourceTable {
         };
 
         self.resources.insert(handle, resource);
+
+        Ok(())
     }
 
     pub fn release_resource(
@@ -72,6 +89,14 @@ impl ResourceTable {
                         AnyResource::OwnedTexture(texture),
                     );
                 }
+                //临时查询集的结果在帧结束后无法读取,丢弃后归还缓存
+                AnyResource::OwnedQuerySet(query_set) => {
+                    query_set.reset();
+                    transient_resource_cache.insert_resource(
+                        query_set.get_desc().clone().into(),
+                        AnyResource::OwnedQuerySet(query_set),
+                    );
+                }
                 _ => {}
             }
         }
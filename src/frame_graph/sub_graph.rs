@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+
+use crate::error::RendererError;
+
+use super::FrameGraph;
+
+///可复用的子帧图,输入输出通过资源面板上的名称声明
+pub trait SubGraph {
+    fn inputs(&self) -> Vec<String> {
+        vec![]
+    }
+
+    fn outputs(&self) -> Vec<String> {
+        vec![]
+    }
+
+    ///在子帧图的命名空间中添加渲染节点
+    fn setup(&self, frame_graph: &mut FrameGraph) -> Result<(), RendererError>;
+}
+
+///子帧图内部名称与外部名称的映射,未指定时使用相同名称
+#[derive(Default, Clone)]
+pub struct SubGraphBindings {
+    inputs: HashMap<String, String>,
+    outputs: HashMap<String, String>,
+}
+
+impl SubGraphBindings {
+    pub fn input(mut self, name: &str, outer_name: &str) -> Self {
+        self.inputs.insert(name.to_owned(), outer_name.to_owned());
+        self
+    }
+
+    pub fn output(mut self, name: &str, outer_name: &str) -> Self {
+        self.outputs.insert(name.to_owned(), outer_name.to_owned());
+        self
+    }
+
+    fn outer_input<'a>(&'a self, name: &'a str) -> &'a str {
+        self.inputs
+            .get(name)
+            .map(|name| name.as_str())
+            .unwrap_or(name)
+    }
+
+    fn outer_output<'a>(&'a self, name: &'a str) -> &'a str {
+        self.outputs
+            .get(name)
+            .map(|name| name.as_str())
+            .unwrap_or(name)
+    }
+}
+
+impl FrameGraph {
+    ///以instance为命名空间实例化子帧图,同一子帧图可以多次实例化,
+    ///子帧图中的渲染节点和资源名称都带有命名空间前缀
+    pub fn add_sub_graph<T: SubGraph>(
+        &mut self,
+        instance: &str,
+        sub_graph: &T,
+        bindings: &SubGraphBindings,
+    ) -> Result<(), RendererError> {
+        let mut inputs = vec![];
+
+        for name in sub_graph.inputs() {
+            let entry = self
+                .get_resource_board()
+                .get_entry(bindings.outer_input(&name))
+                .cloned()
+                .ok_or_else(|| RendererError::SubGraphInputNotFound {
+                    sub_graph: instance.to_owned(),
+                    name: name.clone(),
+                })?;
+
+            inputs.push((name, entry));
+        }
+
+        self.get_resource_board_mut().push_scope(instance);
+
+        let result = inputs
+            .into_iter()
+            .try_for_each(|(name, entry)| self.get_resource_board_mut().put_entry(&name, entry))
+            .and_then(|_| sub_graph.setup(self))
+            .and_then(|_| {
+                sub_graph
+                    .outputs()
+                    .into_iter()
+                    .map(|name| match self.get_resource_board().get_entry(&name) {
+                        Some(entry) => Ok((name, entry.clone())),
+                        None => Err(RendererError::SubGraphOutputNotFound {
+                            sub_graph: instance.to_owned(),
+                            name,
+                        }),
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            });
+
+        self.get_resource_board_mut().pop_scope();
+
+        //导出到外部的名称已被其他资源占用时返回错误
+        for (name, entry) in result? {
+            self.get_resource_board_mut()
+                .put_entry(bindings.outer_output(&name), entry)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        error::RendererError,
+        gfx_base::{Buffer, BufferInfo},
+    };
+
+    use super::{FrameGraph, SubGraph, SubGraphBindings};
+
+    ///读取input,写入后以output导出
+    struct CopySubGraph;
+
+    impl SubGraph for CopySubGraph {
+        fn inputs(&self) -> Vec<String> {
+            vec!["input".to_owned()]
+        }
+
+        fn outputs(&self) -> Vec<String> {
+            vec!["output".to_owned()]
+        }
+
+        fn setup(&self, frame_graph: &mut FrameGraph) -> Result<(), RendererError> {
+            frame_graph.add_pass(
+                0,
+                "copy",
+                |builder| {
+                    builder.read_from_board::<Buffer>("input")?;
+
+                    let output = builder.create(
+                        "output",
+                        BufferInfo {
+                            label: None,
+                            size: 64,
+                            usage: wgpu::BufferUsages::STORAGE,
+                            mapped_at_creation: false,
+                        },
+                    );
+                    let output = builder.write(output).handle();
+                    builder.export("output", output)
+                },
+                |_, _| Ok(()),
+            )
+        }
+    }
+
+    fn graph_with_input() -> FrameGraph {
+        let mut graph = FrameGraph::default();
+        let input = graph.create(
+            "input",
+            BufferInfo {
+                label: None,
+                size: 64,
+                usage: wgpu::BufferUsages::STORAGE,
+                mapped_at_creation: false,
+            },
+        );
+        graph.export("input", input).unwrap();
+        graph
+    }
+
+    #[test]
+    fn instances_use_scoped_names() {
+        let mut graph = graph_with_input();
+
+        graph
+            .add_sub_graph("a", &CopySubGraph, &SubGraphBindings::default())
+            .unwrap();
+        graph
+            .add_sub_graph(
+                "b",
+                &CopySubGraph,
+                &SubGraphBindings::default().output("output", "b_output"),
+            )
+            .unwrap();
+
+        let board = graph.get_resource_board();
+        let a = board.get::<Buffer>("a/output").unwrap();
+        let b = board.get::<Buffer>("b/output").unwrap();
+        assert!(a.resource_handle() != b.resource_handle());
+
+        assert!(board.get::<Buffer>("output").unwrap().resource_handle() == a.resource_handle());
+        assert!(board.get::<Buffer>("b_output").unwrap().resource_handle() == b.resource_handle());
+        assert!(board.get_entry("a/input").is_some());
+
+        assert_eq!(
+            graph.get_resource(&a.resource_handle()).info.name,
+            "a/output"
+        );
+        assert_eq!(
+            graph.get_resource(&b.resource_handle()).info.name,
+            "b/output"
+        );
+
+        let pass_names = graph
+            .pass_nodes
+            .iter()
+            .map(|pass_node| pass_node.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(pass_names, vec!["a/copy", "b/copy"]);
+    }
+
+    #[test]
+    fn conflicting_outputs() {
+        let mut graph = graph_with_input();
+
+        graph
+            .add_sub_graph("a", &CopySubGraph, &SubGraphBindings::default())
+            .unwrap();
+        let result = graph.add_sub_graph("b", &CopySubGraph, &SubGraphBindings::default());
+
+        assert!(matches!(
+            result,
+            Err(RendererError::ResourceBoardConflict { .. })
+        ));
+
+        let output = graph.get_resource_board().get::<Buffer>("output").unwrap();
+        assert_eq!(
+            graph.get_resource(&output.resource_handle()).info.name,
+            "a/output"
+        );
+    }
+
+    #[test]
+    fn missing_input() {
+        let mut graph = FrameGraph::default();
+
+        let result = graph.add_sub_graph("a", &CopySubGraph, &SubGraphBindings::default());
+
+        assert!(matches!(
+            result,
+            Err(RendererError::SubGraphInputNotFound { .. })
+        ));
+        assert!(graph.get_resource_board().get_entry("a/input").is_none());
+    }
+
+    #[test]
+    fn bound_input() {
+        let mut graph = graph_with_input();
+
+        let result = graph.add_sub_graph(
+            "a",
+            &CopySubGraph,
+            &SubGraphBindings::default().input("input", "missing"),
+        );
+
+        assert!(matches!(
+            result,
+            Err(RendererError::SubGraphInputNotFound { .. })
+        ));
+    }
+}
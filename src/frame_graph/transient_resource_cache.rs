@@ -2,6 +2,10 @@ use std::collections::HashMap;
 
 use crate::frame_graph::{AnyResource, AnyResourceDescriptor};
 
+///按去掉名称的描述复用临时资源
+///
+///GPU对象的调试标签在创建后无法修改,复用的资源保留第一次创建时的标签,
+///调试工具中看到的名称可能是之前使用它的资源的名称
 #[derive(Default)]
 pub struct TransientResourceCache {
     resources: HashMap<AnyResourceDescriptor, Vec<AnyResource>>,
@@ -9,7 +13,7 @@ pub struct TransientResourceCache {
 
 impl TransientResourceCache {
     pub fn get_resource(&mut self, desc: &AnyResourceDescriptor) -> Option<AnyResource> {
-        if let Some(entry) = self.resources.get_mut(desc) {
+        if let Some(entry) = self.resources.get_mut(&desc.without_label()) {
             entry.pop()
         } else {
             None
@@ -17,6 +21,8 @@ impl TransientResourceCache {
     }
 
     pub fn insert_resource(&mut self, desc: AnyResourceDescriptor, resource: AnyResource) {
+        let desc = desc.without_label();
+
         if let Some(entry) = self.resources.get_mut(&desc) {
             entry.push(resource);
         } else {
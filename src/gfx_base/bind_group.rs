@@ -4,11 +4,12 @@ use downcast_rs::Downcast;
 
 use crate::{
     define_atomic_id, define_gfx_type,
-    frame_graph::{GpuRead, ResourceNodeRef, ResourceTable},
+    frame_graph::{GpuRead, GpuWrite, ResourceNodeRef, ResourceTable},
 };
 
 use super::{
-    BindGroupLayout, Sample, SampleInfo, Texture, TextureView, TextureViewInfo, device::Device,
+    BindGroupLayout, Buffer, Sample, SampleInfo, Texture, TextureView, TextureViewInfo,
+    device::Device,
 };
 
 define_atomic_id!(BindGroupId);
@@ -21,6 +22,7 @@ impl<T> ErasedBindGroupTrait for T where T: BindGroupTrait {}
 
 define_gfx_type!(BindGroup, BindGroupId, BindGroupTrait, ErasedBindGroupTrait);
 
+#[derive(Clone)]
 pub struct BindGroupRef {
     pub label: Option<Cow<'static, str>>,
     pub layout: BindGroupLayout,
@@ -52,6 +54,38 @@ impl BindGroupRef {
                         ),
                     });
                 }
+                BindingResourceInfo::StorageTexture(handle) => {
+                    let resource = resource_table
+                        .get_resource::<Texture>(&handle.resource_handle())
+                        .unwrap();
+
+                    entries.push(BindGroupEntry {
+                        binding: entry.binding,
+                        resource: BindingResource::TextureView(
+                            resource.get_texture_view(TextureViewInfo {}),
+                        ),
+                    });
+                }
+                BindingResourceInfo::Buffer(handle) => {
+                    let resource = resource_table
+                        .get_resource::<Buffer>(&handle.resource_handle())
+                        .unwrap();
+
+                    entries.push(BindGroupEntry {
+                        binding: entry.binding,
+                        resource: BindingResource::Buffer(resource.clone()),
+                    });
+                }
+                BindingResourceInfo::StorageBuffer(handle) => {
+                    let resource = resource_table
+                        .get_resource::<Buffer>(&handle.resource_handle())
+                        .unwrap();
+
+                    entries.push(BindGroupEntry {
+                        binding: entry.binding,
+                        resource: BindingResource::Buffer(resource.clone()),
+                    });
+                }
             }
         }
 
@@ -69,13 +103,22 @@ pub struct BindGroupInfo {
     pub entries: Vec<BindGroupEntry>,
 }
 
+#[derive(Clone)]
 pub struct BindGroupEntryInfo {
     pub binding: u32,
     pub resource: BindingResourceInfo,
 }
 
+///只读的存储纹理使用TextureView,访问方式由绑定组布局决定
+#[derive(Clone)]
 pub enum BindingResourceInfo {
     TextureView(ResourceNodeRef<Texture, GpuRead>),
+    ///只写或读写的存储纹理
+    StorageTexture(ResourceNodeRef<Texture, GpuWrite>),
+    ///统一缓冲或只读的存储缓冲
+    Buffer(ResourceNodeRef<Buffer, GpuRead>),
+    ///可写的存储缓冲
+    StorageBuffer(ResourceNodeRef<Buffer, GpuWrite>),
     Sampler(SampleInfo),
 }
 
@@ -86,5 +129,12 @@ pub struct BindGroupEntry {
 
 pub enum BindingResource {
     TextureView(TextureView),
+    Buffer(Buffer),
+    ///缓冲区的一部分,带动态偏移的绑定使用它限定每次访问的大小
+    BufferRange {
+        buffer: Buffer,
+        offset: wgpu::BufferAddress,
+        size: wgpu::BufferSize,
+    },
     Sampler(Sample),
 }
@@ -1,9 +1,9 @@
-use std::{borrow::Cow, fmt::Debug};
+use std::{borrow::Cow, collections::HashMap, fmt::Debug, sync::Mutex};
 
 use downcast_rs::Downcast;
 use wgpu::BindGroupLayoutEntry;
 
-use crate::{define_atomic_id, define_gfx_type};
+use crate::{define_atomic_id, define_gfx_frame_graph_type};
 
 define_atomic_id!(BindGroupLayoutId);
 
@@ -26,11 +26,12 @@ where
     }
 }
 
-define_gfx_type!(
+define_gfx_frame_graph_type!(
     BindGroupLayout,
     BindGroupLayoutId,
     BindGroupLayoutTrait,
-    ErasedBindGroupLayoutTrait
+    ErasedBindGroupLayoutTrait,
+    BindGroupLayoutInfo
 );
 
 impl Clone for BindGroupLayout {
@@ -38,10 +39,12 @@ impl Clone for BindGroupLayout {
         BindGroupLayout {
             id: self.id,
             value: self.value.clone_value(),
+            desc: self.desc.clone(),
         }
     }
 }
 
+#[derive(Debug, Clone)]
 pub struct BindGroupLayoutInfo {
     pub label: Option<Cow<'static, str>>,
     pub entries: Vec<BindGroupLayoutEntry>,
@@ -52,3 +55,39 @@ impl BindGroupLayout {
         self.id
     }
 }
+
+///绑定项相同的布局共享同一个BindGroupLayout,标签以第一次创建时为准
+#[derive(Debug, Default)]
+pub struct BindGroupLayoutCache {
+    layouts: Mutex<HashMap<Vec<BindGroupLayoutEntry>, BindGroupLayout>>,
+}
+
+impl BindGroupLayoutCache {
+    pub fn get_or_create(
+        &self,
+        desc: BindGroupLayoutInfo,
+        create: impl FnOnce(BindGroupLayoutInfo) -> BindGroupLayout,
+    ) -> BindGroupLayout {
+        let mut key = desc.entries.clone();
+        key.sort_by_key(|entry| entry.binding);
+
+        self.layouts
+            .lock()
+            .unwrap()
+            .entry(key)
+            .or_insert_with(|| create(desc))
+            .clone()
+    }
+
+    ///移除没有被缓存以外的地方引用的布局
+    pub fn collect(&self, is_unused: impl Fn(&BindGroupLayout) -> bool) {
+        self.layouts
+            .lock()
+            .unwrap()
+            .retain(|_, layout| !is_unused(layout));
+    }
+
+    pub fn clear(&self) {
+        self.layouts.lock().unwrap().clear();
+    }
+}
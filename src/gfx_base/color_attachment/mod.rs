@@ -1,14 +1,64 @@
-use crate::frame_graph::{GpuRead, ResourceNodeRef};
+use crate::{
+    frame_graph::{GpuRead, ResourceNodeRef, VirtualResource},
+    gfx_base::handle::TypeHandle,
+};
 
 use super::texture_view::TextureView;
 
+///附件未指定操作时的清除颜色
+pub const DEFAULT_CLEAR_COLOR: wgpu::Color = wgpu::Color {
+    r: 0.1,
+    g: 0.2,
+    b: 0.3,
+    a: 1.0,
+};
+
 #[derive(Clone)]
 pub enum ColorAttachmentInfo {
-    SwapChain(ResourceNodeRef<TextureView, GpuRead>),
+    SwapChain {
+        handle: ResourceNodeRef<TextureView, GpuRead>,
+        ops: wgpu::Operations<wgpu::Color>,
+    },
 }
 
 impl ColorAttachmentInfo {
     pub fn swap_chain(handle: ResourceNodeRef<TextureView, GpuRead>) -> Self {
-        ColorAttachmentInfo::SwapChain(handle)
+        Self::swap_chain_with_ops(
+            handle,
+            wgpu::Operations {
+                load: wgpu::LoadOp::Clear(DEFAULT_CLEAR_COLOR),
+                store: wgpu::StoreOp::Store,
+            },
+        )
+    }
+
+    pub fn swap_chain_with_ops(
+        handle: ResourceNodeRef<TextureView, GpuRead>,
+        ops: wgpu::Operations<wgpu::Color>,
+    ) -> Self {
+        ColorAttachmentInfo::SwapChain { handle, ops }
+    }
+
+    pub fn resource_handle(&self) -> TypeHandle<VirtualResource> {
+        match self {
+            ColorAttachmentInfo::SwapChain { handle, .. } => handle.resource_handle(),
+        }
+    }
+
+    pub fn ops(&self) -> &wgpu::Operations<wgpu::Color> {
+        match self {
+            ColorAttachmentInfo::SwapChain { ops, .. } => ops,
+        }
+    }
+
+    pub fn ops_mut(&mut self) -> &mut wgpu::Operations<wgpu::Color> {
+        match self {
+            ColorAttachmentInfo::SwapChain { ops, .. } => ops,
+        }
+    }
+
+    ///保留附件原有内容,只有这样的渲染节点才能合并到前一个渲染通道
+    pub fn is_load(&self) -> bool {
+        matches!(self.ops().load, wgpu::LoadOp::Load)
     }
 }
@@ -1,14 +1,23 @@
 use crate::{define_atomic_id, define_gfx_type};
 
 use super::{
-    BindGroup, buffer::Buffer, device::Device, pipeline::RenderPipeline, render_pass::RenderPass,
+    BindGroup, ComputePassDescriptor, QuerySet,
+    buffer::Buffer,
+    device::Device,
+    pipeline::{ComputePipeline, RenderPipeline},
+    render_pass::RenderPass,
 };
 use downcast_rs::Downcast;
-use std::{fmt::Debug, ops::Range};
-use wgpu::IndexFormat;
+use std::{borrow::Cow, fmt::Debug, ops::Range};
+use wgpu::{DynamicOffset, IndexFormat, ShaderStages};
 
 define_atomic_id!(CommandBufferId);
 
+#[derive(Debug, Default, Clone)]
+pub struct CommandBufferInfo {
+    pub label: Option<Cow<'static, str>>,
+}
+
 pub trait CommandBufferTrait: 'static + Sync + Send + Debug {
     fn begin_render_pass(&mut self, device: &Device, render_pass: RenderPass);
 
@@ -16,15 +25,76 @@ pub trait CommandBufferTrait: 'static + Sync + Send + Debug {
 
     fn end_render_pass(&mut self);
 
+    fn begin_compute_pass(&mut self, desc: &ComputePassDescriptor);
+
+    fn set_compute_pipeline(&mut self, compute_pipeline: &ComputePipeline);
+
+    fn dispatch_workgroups(&mut self, x: u32, y: u32, z: u32);
+
+    fn end_compute_pass(&mut self);
+
     fn draw(&mut self, vertices: Range<u32>, instances: Range<u32>);
 
     fn draw_indexed(&mut self, indices: Range<u32>, base_vertex: i32, instances: Range<u32>);
 
+    fn draw_indirect(&mut self, indirect_buffer: &Buffer, indirect_offset: u64);
+
+    fn draw_indexed_indirect(&mut self, indirect_buffer: &Buffer, indirect_offset: u64);
+
+    fn multi_draw_indirect(&mut self, indirect_buffer: &Buffer, indirect_offset: u64, count: u32);
+
+    fn multi_draw_indexed_indirect(
+        &mut self,
+        indirect_buffer: &Buffer,
+        indirect_offset: u64,
+        count: u32,
+    );
+
+    fn multi_draw_indirect_count(
+        &mut self,
+        indirect_buffer: &Buffer,
+        indirect_offset: u64,
+        count_buffer: &Buffer,
+        count_offset: u64,
+        max_count: u32,
+    );
+
+    fn multi_draw_indexed_indirect_count(
+        &mut self,
+        indirect_buffer: &Buffer,
+        indirect_offset: u64,
+        count_buffer: &Buffer,
+        count_offset: u64,
+        max_count: u32,
+    );
+
     fn set_vertex_buffer(&mut self, slot: u32, buffer: &Buffer);
 
     fn set_index_buffer(&mut self, buffer: &Buffer, index_format: IndexFormat);
 
-    fn set_bind_group(&mut self, index: u32, bind_group: &BindGroup);
+    fn set_bind_group(&mut self, index: u32, bind_group: &BindGroup, offsets: &[DynamicOffset]);
+
+    ///计算通道中忽略stages
+    fn set_push_constants(&mut self, stages: ShaderStages, offset: u32, data: &[u8]);
+
+    ///将查询集的前count个结果解析到回读缓冲,在渲染通道中调用时延迟到渲染通道结束
+    fn resolve_query_set(&mut self, query_set: &QuerySet, count: u32);
+
+    fn push_debug_group(&mut self, label: &str);
+
+    fn pop_debug_group(&mut self);
+
+    fn insert_debug_marker(&mut self, label: &str);
+
+    fn begin_occlusion_query(&mut self, query_index: u32);
+
+    fn end_occlusion_query(&mut self);
+
+    fn begin_pipeline_statistics_query(&mut self, query_set: &QuerySet, query_index: u32);
+
+    fn end_pipeline_statistics_query(&mut self);
+
+    fn finish(&mut self);
 }
 
 pub trait ErasedCommandBufferTrait: 'static + Sync + Send + Debug + Downcast {
@@ -34,13 +104,72 @@ pub trait ErasedCommandBufferTrait: 'static + Sync + Send + Debug + Downcast {
 
     fn end_render_pass(&mut self);
 
+    fn begin_compute_pass(&mut self, desc: &ComputePassDescriptor);
+
+    fn set_compute_pipeline(&mut self, compute_pipeline: &ComputePipeline);
+
+    fn dispatch_workgroups(&mut self, x: u32, y: u32, z: u32);
+
+    fn end_compute_pass(&mut self);
+
     fn set_vertex_buffer(&mut self, slot: u32, buffer: &Buffer);
 
-    fn set_bind_group(&mut self, index: u32, bind_group: &BindGroup);
+    fn set_bind_group(&mut self, index: u32, bind_group: &BindGroup, offsets: &[DynamicOffset]);
+
+    fn set_push_constants(&mut self, stages: ShaderStages, offset: u32, data: &[u8]);
 
     fn set_index_buffer(&mut self, buffer: &Buffer, index_format: IndexFormat);
 
     fn draw_indexed(&mut self, indices: Range<u32>, base_vertex: i32, instances: Range<u32>);
+
+    fn draw_indirect(&mut self, indirect_buffer: &Buffer, indirect_offset: u64);
+
+    fn draw_indexed_indirect(&mut self, indirect_buffer: &Buffer, indirect_offset: u64);
+
+    fn multi_draw_indirect(&mut self, indirect_buffer: &Buffer, indirect_offset: u64, count: u32);
+
+    fn multi_draw_indexed_indirect(
+        &mut self,
+        indirect_buffer: &Buffer,
+        indirect_offset: u64,
+        count: u32,
+    );
+
+    fn multi_draw_indirect_count(
+        &mut self,
+        indirect_buffer: &Buffer,
+        indirect_offset: u64,
+        count_buffer: &Buffer,
+        count_offset: u64,
+        max_count: u32,
+    );
+
+    fn multi_draw_indexed_indirect_count(
+        &mut self,
+        indirect_buffer: &Buffer,
+        indirect_offset: u64,
+        count_buffer: &Buffer,
+        count_offset: u64,
+        max_count: u32,
+    );
+
+    fn resolve_query_set(&mut self, query_set: &QuerySet, count: u32);
+
+    fn push_debug_group(&mut self, label: &str);
+
+    fn pop_debug_group(&mut self);
+
+    fn insert_debug_marker(&mut self, label: &str);
+
+    fn begin_occlusion_query(&mut self, query_index: u32);
+
+    fn end_occlusion_query(&mut self);
+
+    fn begin_pipeline_statistics_query(&mut self, query_set: &QuerySet, query_index: u32);
+
+    fn end_pipeline_statistics_query(&mut self);
+
+    fn finish(&mut self);
 }
 
 impl<T: CommandBufferTrait> ErasedCommandBufferTrait for T {
@@ -48,12 +177,83 @@ impl<T: CommandBufferTrait> ErasedCommandBufferTrait for T {
         <T as CommandBufferTrait>::draw_indexed(self, indices, base_vertex, instances);
     }
 
+    fn draw_indirect(&mut self, indirect_buffer: &Buffer, indirect_offset: u64) {
+        <T as CommandBufferTrait>::draw_indirect(self, indirect_buffer, indirect_offset);
+    }
+
+    fn draw_indexed_indirect(&mut self, indirect_buffer: &Buffer, indirect_offset: u64) {
+        <T as CommandBufferTrait>::draw_indexed_indirect(self, indirect_buffer, indirect_offset);
+    }
+
+    fn multi_draw_indirect(&mut self, indirect_buffer: &Buffer, indirect_offset: u64, count: u32) {
+        <T as CommandBufferTrait>::multi_draw_indirect(
+            self,
+            indirect_buffer,
+            indirect_offset,
+            count,
+        );
+    }
+
+    fn multi_draw_indexed_indirect(
+        &mut self,
+        indirect_buffer: &Buffer,
+        indirect_offset: u64,
+        count: u32,
+    ) {
+        <T as CommandBufferTrait>::multi_draw_indexed_indirect(
+            self,
+            indirect_buffer,
+            indirect_offset,
+            count,
+        );
+    }
+
+    fn multi_draw_indirect_count(
+        &mut self,
+        indirect_buffer: &Buffer,
+        indirect_offset: u64,
+        count_buffer: &Buffer,
+        count_offset: u64,
+        max_count: u32,
+    ) {
+        <T as CommandBufferTrait>::multi_draw_indirect_count(
+            self,
+            indirect_buffer,
+            indirect_offset,
+            count_buffer,
+            count_offset,
+            max_count,
+        );
+    }
+
+    fn multi_draw_indexed_indirect_count(
+        &mut self,
+        indirect_buffer: &Buffer,
+        indirect_offset: u64,
+        count_buffer: &Buffer,
+        count_offset: u64,
+        max_count: u32,
+    ) {
+        <T as CommandBufferTrait>::multi_draw_indexed_indirect_count(
+            self,
+            indirect_buffer,
+            indirect_offset,
+            count_buffer,
+            count_offset,
+            max_count,
+        );
+    }
+
     fn set_index_buffer(&mut self, buffer: &Buffer, index_format: IndexFormat) {
         <T as CommandBufferTrait>::set_index_buffer(self, buffer, index_format);
     }
 
-    fn set_bind_group(&mut self, index: u32, bind_group: &BindGroup) {
-        <T as CommandBufferTrait>::set_bind_group(self, index, bind_group);
+    fn set_bind_group(&mut self, index: u32, bind_group: &BindGroup, offsets: &[DynamicOffset]) {
+        <T as CommandBufferTrait>::set_bind_group(self, index, bind_group, offsets);
+    }
+
+    fn set_push_constants(&mut self, stages: ShaderStages, offset: u32, data: &[u8]) {
+        <T as CommandBufferTrait>::set_push_constants(self, stages, offset, data);
     }
 
     fn begin_render_pass(&mut self, device: &Device, render_pass: RenderPass) {
@@ -70,9 +270,61 @@ impl<T: CommandBufferTrait> ErasedCommandBufferTrait for T {
         <T as CommandBufferTrait>::end_render_pass(self);
     }
 
+    fn begin_compute_pass(&mut self, desc: &ComputePassDescriptor) {
+        <T as CommandBufferTrait>::begin_compute_pass(self, desc);
+    }
+
+    fn set_compute_pipeline(&mut self, compute_pipeline: &ComputePipeline) {
+        <T as CommandBufferTrait>::set_compute_pipeline(self, compute_pipeline);
+    }
+
+    fn dispatch_workgroups(&mut self, x: u32, y: u32, z: u32) {
+        <T as CommandBufferTrait>::dispatch_workgroups(self, x, y, z);
+    }
+
+    fn end_compute_pass(&mut self) {
+        <T as CommandBufferTrait>::end_compute_pass(self);
+    }
+
     fn set_vertex_buffer(&mut self, slot: u32, buffer: &Buffer) {
         <T as CommandBufferTrait>::set_vertex_buffer(self, slot, buffer);
     }
+
+    fn resolve_query_set(&mut self, query_set: &QuerySet, count: u32) {
+        <T as CommandBufferTrait>::resolve_query_set(self, query_set, count);
+    }
+
+    fn push_debug_group(&mut self, label: &str) {
+        <T as CommandBufferTrait>::push_debug_group(self, label);
+    }
+
+    fn pop_debug_group(&mut self) {
+        <T as CommandBufferTrait>::pop_debug_group(self);
+    }
+
+    fn insert_debug_marker(&mut self, label: &str) {
+        <T as CommandBufferTrait>::insert_debug_marker(self, label);
+    }
+
+    fn begin_occlusion_query(&mut self, query_index: u32) {
+        <T as CommandBufferTrait>::begin_occlusion_query(self, query_index);
+    }
+
+    fn end_occlusion_query(&mut self) {
+        <T as CommandBufferTrait>::end_occlusion_query(self);
+    }
+
+    fn begin_pipeline_statistics_query(&mut self, query_set: &QuerySet, query_index: u32) {
+        <T as CommandBufferTrait>::begin_pipeline_statistics_query(self, query_set, query_index);
+    }
+
+    fn end_pipeline_statistics_query(&mut self) {
+        <T as CommandBufferTrait>::end_pipeline_statistics_query(self);
+    }
+
+    fn finish(&mut self) {
+        <T as CommandBufferTrait>::finish(self);
+    }
 }
 
 define_gfx_type!(
@@ -91,6 +343,22 @@ impl CommandBuffer {
         self.value.end_render_pass();
     }
 
+    pub fn begin_compute_pass(&mut self, desc: &ComputePassDescriptor) {
+        self.value.begin_compute_pass(desc);
+    }
+
+    pub fn set_compute_pipeline(&mut self, compute_pipeline: &ComputePipeline) {
+        self.value.set_compute_pipeline(compute_pipeline);
+    }
+
+    pub fn dispatch_workgroups(&mut self, x: u32, y: u32, z: u32) {
+        self.value.dispatch_workgroups(x, y, z);
+    }
+
+    pub fn end_compute_pass(&mut self) {
+        self.value.end_compute_pass();
+    }
+
     pub fn set_render_pipeline(&mut self, render_pipeline: &RenderPipeline) {
         self.value.set_render_pipeline(render_pipeline);
     }
@@ -103,15 +371,125 @@ impl CommandBuffer {
         self.value.draw_indexed(indices, base_vertex, instances);
     }
 
+    pub fn draw_indirect(&mut self, indirect_buffer: &Buffer, indirect_offset: u64) {
+        self.value.draw_indirect(indirect_buffer, indirect_offset);
+    }
+
+    pub fn draw_indexed_indirect(&mut self, indirect_buffer: &Buffer, indirect_offset: u64) {
+        self.value
+            .draw_indexed_indirect(indirect_buffer, indirect_offset);
+    }
+
+    pub fn multi_draw_indirect(
+        &mut self,
+        indirect_buffer: &Buffer,
+        indirect_offset: u64,
+        count: u32,
+    ) {
+        self.value
+            .multi_draw_indirect(indirect_buffer, indirect_offset, count);
+    }
+
+    pub fn multi_draw_indexed_indirect(
+        &mut self,
+        indirect_buffer: &Buffer,
+        indirect_offset: u64,
+        count: u32,
+    ) {
+        self.value
+            .multi_draw_indexed_indirect(indirect_buffer, indirect_offset, count);
+    }
+
+    pub fn multi_draw_indirect_count(
+        &mut self,
+        indirect_buffer: &Buffer,
+        indirect_offset: u64,
+        count_buffer: &Buffer,
+        count_offset: u64,
+        max_count: u32,
+    ) {
+        self.value.multi_draw_indirect_count(
+            indirect_buffer,
+            indirect_offset,
+            count_buffer,
+            count_offset,
+            max_count,
+        );
+    }
+
+    pub fn multi_draw_indexed_indirect_count(
+        &mut self,
+        indirect_buffer: &Buffer,
+        indirect_offset: u64,
+        count_buffer: &Buffer,
+        count_offset: u64,
+        max_count: u32,
+    ) {
+        self.value.multi_draw_indexed_indirect_count(
+            indirect_buffer,
+            indirect_offset,
+            count_buffer,
+            count_offset,
+            max_count,
+        );
+    }
+
     pub fn set_vertex_buffer(&mut self, slot: u32, buffer: &Buffer) {
         self.value.set_vertex_buffer(slot, buffer);
     }
 
-    pub fn set_bind_group(&mut self, index: u32, bind_group: &BindGroup) {
-        self.value.set_bind_group(index, bind_group);
+    ///offsets按绑定编号顺序对应布局中带动态偏移的绑定
+    pub fn set_bind_group(
+        &mut self,
+        index: u32,
+        bind_group: &BindGroup,
+        offsets: &[DynamicOffset],
+    ) {
+        self.value.set_bind_group(index, bind_group, offsets);
+    }
+
+    pub fn set_push_constants(&mut self, stages: ShaderStages, offset: u32, data: &[u8]) {
+        self.value.set_push_constants(stages, offset, data);
     }
 
     pub fn set_index_buffer(&mut self, buffer: &Buffer, index_format: IndexFormat) {
         self.value.set_index_buffer(buffer, index_format);
     }
+
+    pub fn resolve_query_set(&mut self, query_set: &QuerySet, count: u32) {
+        self.value.resolve_query_set(query_set, count);
+    }
+
+    pub fn push_debug_group(&mut self, label: &str) {
+        self.value.push_debug_group(label);
+    }
+
+    pub fn pop_debug_group(&mut self) {
+        self.value.pop_debug_group();
+    }
+
+    pub fn insert_debug_marker(&mut self, label: &str) {
+        self.value.insert_debug_marker(label);
+    }
+
+    pub fn begin_occlusion_query(&mut self, query_index: u32) {
+        self.value.begin_occlusion_query(query_index);
+    }
+
+    pub fn end_occlusion_query(&mut self) {
+        self.value.end_occlusion_query();
+    }
+
+    pub fn begin_pipeline_statistics_query(&mut self, query_set: &QuerySet, query_index: u32) {
+        self.value
+            .begin_pipeline_statistics_query(query_set, query_index);
+    }
+
+    pub fn end_pipeline_statistics_query(&mut self) {
+        self.value.end_pipeline_statistics_query();
+    }
+
+    pub fn finish(&mut self) {
+        self.value.finish();
+    }
 }
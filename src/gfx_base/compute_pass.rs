@@ -0,0 +1,9 @@
+use std::borrow::Cow;
+
+use super::PassTimestampWrites;
+
+#[derive(Default, Clone)]
+pub struct ComputePassDescriptor {
+    pub label: Option<Cow<'static, str>>,
+    pub timestamp_writes: Option<PassTimestampWrites>,
+}
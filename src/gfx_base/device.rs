@@ -3,29 +3,49 @@ use std::fmt::Debug;
 
 use downcast_rs::Downcast;
 
-use crate::frame_graph::{AnyResource, AnyResourceDescriptor};
+use crate::{
+    error::RendererError,
+    frame_graph::{AnyResource, AnyResourceDescriptor},
+};
 
 use super::{
-    BindGroup, BindGroupInfo, BindGroupLayout, BindGroupLayoutInfo, PipelineLayout,
-    PipelineLayoutDescriptor, RenderPass, RenderPassDescriptor, RenderPipeline,
-    RenderPipelineDescriptorState, Sample, SampleInfo, ShaderModule, ShaderModuleDescriptor,
-    Texture, TextureInfo,
+    BindGroup, BindGroupInfo, BindGroupLayout, BindGroupLayoutInfo, ComputePipeline,
+    ComputePipelineDescriptorState, PipelineLayout, PipelineLayoutDescriptor, QuerySet,
+    QuerySetInfo, RenderPass, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptorState,
+    Sample, SampleInfo, ShaderModule, ShaderModuleDescriptor, Texture, TextureInfo,
     buffer::{Buffer, BufferInfo, BufferInitInfo},
-    command_buffer::CommandBuffer,
+    command_buffer::{CommandBuffer, CommandBufferInfo},
 };
 
 define_atomic_id!(DeviceId);
 
+///命令提交的队列类型
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QueueType {
+    #[default]
+    Graphics,
+    AsyncCompute,
+    Transfer,
+}
+
 pub trait DeviceTrait: 'static + Sync + Send + Debug {
     fn create_bind_group_layout(&self, desc: BindGroupLayoutInfo) -> BindGroupLayout;
 
+    ///释放只被设备缓存持有的绑定组布局
+    fn collect_bind_group_layouts(&self);
+
     fn create_render_pass(&self, desc: RenderPassDescriptor) -> RenderPass;
 
     fn create_render_pipeline(&self, desc: RenderPipelineDescriptorState) -> RenderPipeline;
 
-    fn create_command_buffer(&self) -> CommandBuffer;
+    fn create_compute_pipeline(&self, desc: ComputePipelineDescriptorState) -> ComputePipeline;
+
+    fn create_command_buffer(&self, desc: CommandBufferInfo) -> CommandBuffer;
 
-    fn create_shader_module(&self, desc: ShaderModuleDescriptor) -> ShaderModule;
+    fn create_shader_module(
+        &self,
+        desc: ShaderModuleDescriptor,
+    ) -> Result<ShaderModule, RendererError>;
 
     fn create_pipeline_layout(&self, desc: PipelineLayoutDescriptor) -> PipelineLayout;
 
@@ -33,13 +53,34 @@ pub trait DeviceTrait: 'static + Sync + Send + Debug {
 
     fn create_texture(&self, desc: TextureInfo) -> Texture;
 
-    fn submit(&self, command_buffers: Vec<CommandBuffer>);
+    ///帧图的跨队列同步只保证提交顺序,后端在提交之间提供信号量之前只应对图形队列返回true
+    fn has_queue(&self, queue_type: QueueType) -> bool;
+
+    fn submit(&self, queue_type: QueueType, command_buffers: Vec<CommandBuffer>);
 
     fn create_buffer_init(&self, desc: BufferInitInfo) -> Buffer;
 
+    ///写入在下一次提交之前完成
+    fn write_buffer(&self, buffer: &Buffer, offset: wgpu::BufferAddress, data: &[u8]);
+
     fn create_bind_group(&self, desc: BindGroupInfo) -> BindGroup;
 
     fn create_sampler(&self, desc: SampleInfo) -> Sample;
+
+    fn create_query_set(&self, desc: QuerySetInfo) -> QuerySet;
+
+    fn features(&self) -> wgpu::Features;
+
+    ///时间戳查询每个单位对应的纳秒数
+    fn timestamp_period(&self) -> f32;
+
+    ///由适配器和驱动版本决定的磁盘管线缓存名称,不支持管线缓存时为空
+    fn pipeline_cache_key(&self) -> Option<String>;
+
+    ///使用磁盘中读取的数据初始化管线缓存,之后创建的管线都会使用它
+    fn init_pipeline_cache(&self, data: Option<&[u8]>);
+
+    fn pipeline_cache_data(&self) -> Option<Vec<u8>>;
 }
 
 pub trait ErasedDeviceTrait: 'static + Sync + Send + Debug + Downcast {
@@ -49,13 +90,20 @@ pub trait ErasedDeviceTrait: 'static + Sync + Send + Debug + Downcast {
 
     fn create_render_pipeline(&self, desc: RenderPipelineDescriptorState) -> RenderPipeline;
 
-    fn create_command_buffer(&self) -> CommandBuffer;
+    fn create_compute_pipeline(&self, desc: ComputePipelineDescriptorState) -> ComputePipeline;
 
-    fn create_shader_module(&self, desc: ShaderModuleDescriptor) -> ShaderModule;
+    fn create_command_buffer(&self, desc: CommandBufferInfo) -> CommandBuffer;
+
+    fn create_shader_module(
+        &self,
+        desc: ShaderModuleDescriptor,
+    ) -> Result<ShaderModule, RendererError>;
 
     fn create_pipeline_layout(&self, desc: PipelineLayoutDescriptor) -> PipelineLayout;
 
-    fn submit(&self, command_buffers: Vec<CommandBuffer>);
+    fn has_queue(&self, queue_type: QueueType) -> bool;
+
+    fn submit(&self, queue_type: QueueType, command_buffers: Vec<CommandBuffer>);
 
     fn create_buffer(&self, desc: BufferInfo) -> Buffer;
 
@@ -63,9 +111,25 @@ pub trait ErasedDeviceTrait: 'static + Sync + Send + Debug + Downcast {
 
     fn create_buffer_init(&self, desc: BufferInitInfo) -> Buffer;
 
+    fn write_buffer(&self, buffer: &Buffer, offset: wgpu::BufferAddress, data: &[u8]);
+
     fn create_bind_group_layout(&self, desc: BindGroupLayoutInfo) -> BindGroupLayout;
 
+    fn collect_bind_group_layouts(&self);
+
     fn create_bind_group(&self, desc: BindGroupInfo) -> BindGroup;
+
+    fn create_query_set(&self, desc: QuerySetInfo) -> QuerySet;
+
+    fn features(&self) -> wgpu::Features;
+
+    fn timestamp_period(&self) -> f32;
+
+    fn pipeline_cache_key(&self) -> Option<String>;
+
+    fn init_pipeline_cache(&self, data: Option<&[u8]>);
+
+    fn pipeline_cache_data(&self) -> Option<Vec<u8>>;
 }
 
 impl<T: DeviceTrait> ErasedDeviceTrait for T {
@@ -81,6 +145,10 @@ impl<T: DeviceTrait> ErasedDeviceTrait for T {
         <T as DeviceTrait>::create_bind_group_layout(self, desc)
     }
 
+    fn collect_bind_group_layouts(&self) {
+        <T as DeviceTrait>::collect_bind_group_layouts(self)
+    }
+
     fn create_buffer(&self, desc: BufferInfo) -> Buffer {
         <T as DeviceTrait>::create_buffer(self, desc)
     }
@@ -97,11 +165,18 @@ impl<T: DeviceTrait> ErasedDeviceTrait for T {
         <T as DeviceTrait>::create_render_pipeline(self, desc)
     }
 
-    fn create_command_buffer(&self) -> CommandBuffer {
-        <T as DeviceTrait>::create_command_buffer(self)
+    fn create_compute_pipeline(&self, desc: ComputePipelineDescriptorState) -> ComputePipeline {
+        <T as DeviceTrait>::create_compute_pipeline(self, desc)
     }
 
-    fn create_shader_module(&self, desc: ShaderModuleDescriptor) -> ShaderModule {
+    fn create_command_buffer(&self, desc: CommandBufferInfo) -> CommandBuffer {
+        <T as DeviceTrait>::create_command_buffer(self, desc)
+    }
+
+    fn create_shader_module(
+        &self,
+        desc: ShaderModuleDescriptor,
+    ) -> Result<ShaderModule, RendererError> {
         <T as DeviceTrait>::create_shader_module(self, desc)
     }
 
@@ -113,8 +188,40 @@ impl<T: DeviceTrait> ErasedDeviceTrait for T {
         <T as DeviceTrait>::create_buffer_init(self, desc)
     }
 
-    fn submit(&self, command_buffers: Vec<CommandBuffer>) {
-        <T as DeviceTrait>::submit(self, command_buffers)
+    fn write_buffer(&self, buffer: &Buffer, offset: wgpu::BufferAddress, data: &[u8]) {
+        <T as DeviceTrait>::write_buffer(self, buffer, offset, data)
+    }
+
+    fn create_query_set(&self, desc: QuerySetInfo) -> QuerySet {
+        <T as DeviceTrait>::create_query_set(self, desc)
+    }
+
+    fn features(&self) -> wgpu::Features {
+        <T as DeviceTrait>::features(self)
+    }
+
+    fn timestamp_period(&self) -> f32 {
+        <T as DeviceTrait>::timestamp_period(self)
+    }
+
+    fn pipeline_cache_key(&self) -> Option<String> {
+        <T as DeviceTrait>::pipeline_cache_key(self)
+    }
+
+    fn init_pipeline_cache(&self, data: Option<&[u8]>) {
+        <T as DeviceTrait>::init_pipeline_cache(self, data)
+    }
+
+    fn pipeline_cache_data(&self) -> Option<Vec<u8>> {
+        <T as DeviceTrait>::pipeline_cache_data(self)
+    }
+
+    fn has_queue(&self, queue_type: QueueType) -> bool {
+        <T as DeviceTrait>::has_queue(self, queue_type)
+    }
+
+    fn submit(&self, queue_type: QueueType, command_buffers: Vec<CommandBuffer>) {
+        <T as DeviceTrait>::submit(self, queue_type, command_buffers)
     }
 }
 
@@ -126,6 +233,12 @@ impl Device {
             AnyResourceDescriptor::Buffer(desc) => {
                 AnyResource::OwnedBuffer(self.create_buffer(desc))
             }
+            AnyResourceDescriptor::Texture(desc) => {
+                AnyResource::OwnedTexture(self.create_texture(desc))
+            }
+            AnyResourceDescriptor::QuerySet(desc) => {
+                AnyResource::OwnedQuerySet(self.create_query_set(desc))
+            }
             _ => {
                 unimplemented!()
             }
@@ -136,8 +249,21 @@ impl Device {
         self.value.create_render_pass(desc)
     }
 
-    pub fn submit(&self, command_buffers: Vec<CommandBuffer>) {
-        self.value.submit(command_buffers);
+    pub fn has_queue(&self, queue_type: QueueType) -> bool {
+        self.value.has_queue(queue_type)
+    }
+
+    ///不支持的队列回退到图形队列
+    pub fn resolve_queue(&self, queue_type: QueueType) -> QueueType {
+        if self.has_queue(queue_type) {
+            queue_type
+        } else {
+            QueueType::Graphics
+        }
+    }
+
+    pub fn submit(&self, queue_type: QueueType, command_buffers: Vec<CommandBuffer>) {
+        self.value.submit(queue_type, command_buffers);
     }
 
     pub fn create_bind_group(&self, desc: BindGroupInfo) -> BindGroup {
@@ -148,11 +274,21 @@ impl Device {
         self.value.create_render_pipeline(state)
     }
 
-    pub fn create_command_buffer(&self) -> CommandBuffer {
-        self.value.create_command_buffer()
+    pub fn create_compute_pipeline(
+        &self,
+        state: ComputePipelineDescriptorState,
+    ) -> ComputePipeline {
+        self.value.create_compute_pipeline(state)
+    }
+
+    pub fn create_command_buffer(&self, desc: CommandBufferInfo) -> CommandBuffer {
+        self.value.create_command_buffer(desc)
     }
 
-    pub fn create_shader_module(&self, desc: ShaderModuleDescriptor) -> ShaderModule {
+    pub fn create_shader_module(
+        &self,
+        desc: ShaderModuleDescriptor,
+    ) -> Result<ShaderModule, RendererError> {
         self.value.create_shader_module(desc)
     }
 
@@ -172,11 +308,43 @@ impl Device {
         self.value.create_buffer_init(desc)
     }
 
+    pub fn write_buffer(&self, buffer: &Buffer, offset: wgpu::BufferAddress, data: &[u8]) {
+        self.value.write_buffer(buffer, offset, data);
+    }
+
     pub fn create_bind_group_layout(&self, desc: BindGroupLayoutInfo) -> BindGroupLayout {
         self.value.create_bind_group_layout(desc)
     }
 
+    pub fn collect_bind_group_layouts(&self) {
+        self.value.collect_bind_group_layouts();
+    }
+
     pub fn create_sampler(&self, desc: SampleInfo) -> Sample {
         self.value.create_sampler(desc)
     }
+
+    pub fn create_query_set(&self, desc: QuerySetInfo) -> QuerySet {
+        self.value.create_query_set(desc)
+    }
+
+    pub fn features(&self) -> wgpu::Features {
+        self.value.features()
+    }
+
+    pub fn timestamp_period(&self) -> f32 {
+        self.value.timestamp_period()
+    }
+
+    pub fn pipeline_cache_key(&self) -> Option<String> {
+        self.value.pipeline_cache_key()
+    }
+
+    pub fn init_pipeline_cache(&self, data: Option<&[u8]>) {
+        self.value.init_pipeline_cache(data)
+    }
+
+    pub fn pipeline_cache_data(&self) -> Option<Vec<u8>> {
+        self.value.pipeline_cache_data()
+    }
 }
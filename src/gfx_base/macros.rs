@@ -56,6 +56,14 @@ macro_rules! define_gfx_type {
             }
         }
 
+        impl Eq for $gfx_type {}
+
+        impl core::hash::Hash for $gfx_type {
+            fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+                self.id.hash(state);
+            }
+        }
+
         impl $gfx_type {
             pub fn new<T: $gfx_type_trait>(value: T) -> Self {
                 $gfx_type {
@@ -95,6 +103,14 @@ macro_rules! define_gfx_frame_graph_type {
             }
         }
 
+        impl Eq for $gfx_type {}
+
+        impl core::hash::Hash for $gfx_type {
+            fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+                self.id.hash(state);
+            }
+        }
+
         impl $gfx_type {
             pub fn new<T: $gfx_type_trait>(value: T, desc: $desc) -> Self {
                 $gfx_type {
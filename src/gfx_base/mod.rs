@@ -4,11 +4,13 @@ pub mod buffer;
 pub mod cache;
 pub mod color_attachment;
 pub mod command_buffer;
+pub mod compute_pass;
 pub mod device;
 pub mod handle;
 pub mod macros;
 pub mod pipeline;
 pub mod pipeline_layout;
+pub mod query_set;
 pub mod render_pass;
 pub mod sample;
 pub mod shader;
@@ -20,8 +22,10 @@ pub use bind_group::*;
 pub use bind_group_layout::*;
 pub use buffer::*;
 pub use color_attachment::*;
+pub use compute_pass::*;
 pub use pipeline::*;
 pub use pipeline_layout::*;
+pub use query_set::*;
 pub use render_pass::*;
 pub use sample::*;
 pub use shader::*;
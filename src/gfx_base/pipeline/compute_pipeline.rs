@@ -0,0 +1,20 @@
+use std::fmt::Debug;
+
+use downcast_rs::Downcast;
+
+use crate::{define_atomic_id, define_gfx_type};
+
+define_atomic_id!(ComputePipelineId);
+
+pub trait ComputePipelineTrait: 'static + Debug + Sync + Send {}
+
+pub trait ErasedComputePipelineTrait: 'static + Sync + Send + Debug + Downcast {}
+
+impl<T: ComputePipelineTrait> ErasedComputePipelineTrait for T {}
+
+define_gfx_type!(
+    ComputePipeline,
+    ComputePipelineId,
+    ComputePipelineTrait,
+    ErasedComputePipelineTrait
+);
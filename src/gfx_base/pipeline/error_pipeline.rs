@@ -0,0 +1,90 @@
+use std::fmt::Write;
+
+use wgpu::VertexFormat;
+
+use super::RenderPipelineDescriptor;
+
+///着色器编译失败时管线缓存返回的管线
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PipelineFallback {
+    ///不返回任何管线
+    #[default]
+    None,
+    ///返回上一次编译成功的管线
+    LastGood,
+    ///返回输出洋红色的错误管线
+    ErrorPipeline,
+}
+
+pub const ERROR_VERTEX_ENTRY_POINT: &str = "error_vertex";
+pub const ERROR_FRAGMENT_ENTRY_POINT: &str = "error_fragment";
+
+///生成与原管线顶点布局和渲染目标兼容的错误着色器,location 0的顶点属性作为位置
+pub fn error_shader_source(desc: &RenderPipelineDescriptor) -> String {
+    let position_format = desc
+        .vertex
+        .buffers
+        .iter()
+        .flat_map(|layout| layout.attributes.iter())
+        .find(|attribute| attribute.shader_location == 0)
+        .map(|attribute| attribute.format);
+
+    let (input, position) = match position_format {
+        Some(VertexFormat::Float32x2) => (
+            "@location(0) position: vec2<f32>",
+            "vec4<f32>(position, 0.0, 1.0)",
+        ),
+        Some(VertexFormat::Float32x3) => (
+            "@location(0) position: vec3<f32>",
+            "vec4<f32>(position, 1.0)",
+        ),
+        Some(VertexFormat::Float32x4) => ("@location(0) position: vec4<f32>", "position"),
+        _ => ("", "vec4<f32>(0.0, 0.0, 0.0, 1.0)"),
+    };
+
+    let mut source = String::new();
+
+    let _ = writeln!(
+        source,
+        "@vertex\nfn {ERROR_VERTEX_ENTRY_POINT}({input}) -> @builtin(position) vec4<f32> {{\n    return {position};\n}}"
+    );
+
+    let targets = desc
+        .fragment
+        .as_ref()
+        .map(|fragment| {
+            fragment
+                .targets
+                .iter()
+                .enumerate()
+                .filter(|(_, target)| target.is_some())
+                .map(|(index, _)| index)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    if targets.is_empty() {
+        let _ = writeln!(source, "@fragment\nfn {ERROR_FRAGMENT_ENTRY_POINT}() {{}}");
+        return source;
+    }
+
+    let _ = writeln!(source, "struct ErrorOutput {{");
+    for index in targets.iter() {
+        let _ = writeln!(source, "    @location({index}) color_{index}: vec4<f32>,");
+    }
+    let _ = writeln!(source, "}}");
+
+    let _ = writeln!(
+        source,
+        "@fragment\nfn {ERROR_FRAGMENT_ENTRY_POINT}() -> ErrorOutput {{\n    var output: ErrorOutput;"
+    );
+    for index in targets.iter() {
+        let _ = writeln!(
+            source,
+            "    output.color_{index} = vec4<f32>(1.0, 0.0, 1.0, 1.0);"
+        );
+    }
+    let _ = writeln!(source, "    return output;\n}}");
+
+    source
+}
@@ -1,11 +1,19 @@
+mod compute_pipeline;
+mod error_pipeline;
 mod pipeline_cache;
+mod reflection;
 mod render_pipeline;
 mod shader_cache;
+mod shader_diagnostic;
 
+pub use compute_pipeline::*;
+pub use error_pipeline::*;
 use fyrox_resource::Resource;
 pub use pipeline_cache::*;
+pub use reflection::*;
 pub use render_pipeline::*;
 pub use shader_cache::*;
+pub use shader_diagnostic::*;
 use wgpu::{BufferAddress, ColorTargetState, PushConstantRange, VertexAttribute, VertexStepMode};
 
 use std::borrow::Cow;
@@ -27,7 +35,7 @@ pub struct VertexBufferLayout {
     pub attributes: Vec<VertexAttribute>,
 }
 
-#[derive(PartialEq, Clone)]
+#[derive(Clone, Hash, Eq, PartialEq)]
 pub struct RenderPipelineDescriptor {
     pub label: Option<Cow<'static, str>>,
     /// The layout of bind groups for this pipeline.
@@ -44,7 +52,7 @@ pub struct RenderPipelineDescriptorState<'a> {
     pub desc: RenderPipelineDescriptor,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
 pub struct VertexState {
     pub shader: Resource<Shader>,
     pub shader_defs: Vec<ShaderDefVal>,
@@ -52,7 +60,7 @@ pub struct VertexState {
     pub buffers: Vec<VertexBufferLayout>,
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
 pub struct FragmentState {
     /// The compiled shader module for this stage.
     pub shader: Resource<Shader>,
@@ -63,3 +71,19 @@ pub struct FragmentState {
     /// The color state of the render targets.
     pub targets: Vec<Option<ColorTargetState>>,
 }
+
+#[derive(Clone, Hash, Eq, PartialEq)]
+pub struct ComputePipelineDescriptor {
+    pub label: Option<Cow<'static, str>>,
+    pub layout: Vec<BindGroupLayout>,
+    pub push_constant_ranges: Vec<PushConstantRange>,
+    pub shader: Resource<Shader>,
+    pub shader_defs: Vec<ShaderDefVal>,
+    pub entry_point: Cow<'static, str>,
+}
+
+pub struct ComputePipelineDescriptorState<'a> {
+    pub module: &'a ShaderModule,
+    pub layout: Option<&'a PipelineLayout>,
+    pub desc: ComputePipelineDescriptor,
+}
@@ -1,55 +1,106 @@
 use std::{
     collections::{HashMap, HashSet},
-    mem,
-    sync::Arc,
+    fs, mem,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        mpsc::{Receiver, Sender, channel},
+    },
 };
 
-use super::{RenderPipeline, RenderPipelineDescriptor, RenderPipelineDescriptorState, ShaderCache};
+use super::{
+    ComputePipeline, ComputePipelineDescriptor, ComputePipelineDescriptorState,
+    ERROR_FRAGMENT_ENTRY_POINT, ERROR_VERTEX_ENTRY_POINT, PipelineFallback, PipelineReflection,
+    RenderPipeline, RenderPipelineDescriptor, RenderPipelineDescriptorState, ShaderCache,
+    ShaderDiagnostic, error_shader_source,
+};
 use crate::gfx_base::{
-    bind_group_layout::{BindGroupLayout, BindGroupLayoutId},
+    bind_group_layout::{BindGroupLayout, BindGroupLayoutId, BindGroupLayoutInfo},
     device::Device,
     pipeline_layout::{PipelineLayout, PipelineLayoutDescriptor},
     shader::Shader,
+    shader_module::{ShaderModule, ShaderModuleDescriptor, ShaderModuleSource},
 };
+use fyrox_core::task::TaskPool;
 use fyrox_resource::Resource;
 use thiserror::Error;
-use tracing::error;
+use tracing::{error, warn};
 use wgpu::PushConstantRange;
 
 pub type CachedPipelineId = usize;
 
+///设备支持推送常量时定义的着色器宏
+pub const PUSH_CONSTANTS_SHADER_DEF: &str = "PUSH_CONSTANTS";
+///推送常量回退使用的绑定组号,着色器中通过#{PUSH_CONSTANT_GROUP}引用
+pub const PUSH_CONSTANT_GROUP_SHADER_DEF: &str = "PUSH_CONSTANT_GROUP";
+///不支持推送常量时,推送常量改为此组0号绑定的uniform缓冲区
+pub const PUSH_CONSTANT_FALLBACK_GROUP: u32 = 3;
+
+///管线槽位及注册时的代数,槽位被复用后旧的id不再指向任何管线
 #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, PartialOrd, Ord)]
-pub struct CachedRenderPipelineId(CachedPipelineId);
+pub struct CachedRenderPipelineId(CachedPipelineId, u64);
 
 impl CachedRenderPipelineId {
-    pub const INVALID: Self = CachedRenderPipelineId(usize::MAX);
+    pub const INVALID: Self = CachedRenderPipelineId(usize::MAX, 0);
+
+    #[inline]
+    pub fn id(&self) -> usize {
+        self.0
+    }
+
+    #[inline]
+    pub fn generation(&self) -> u64 {
+        self.1
+    }
+
+    pub fn new(id: CachedPipelineId, generation: u64) -> Self {
+        CachedRenderPipelineId(id, generation)
+    }
+}
+
+///管线槽位及注册时的代数,槽位被复用后旧的id不再指向任何管线
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, PartialOrd, Ord)]
+pub struct CachedComputePipelineId(CachedPipelineId, u64);
+
+impl CachedComputePipelineId {
+    pub const INVALID: Self = CachedComputePipelineId(usize::MAX, 0);
 
     #[inline]
     pub fn id(&self) -> usize {
         self.0
     }
 
-    pub fn new(id: CachedPipelineId) -> Self {
-        CachedRenderPipelineId(id)
+    #[inline]
+    pub fn generation(&self) -> u64 {
+        self.1
+    }
+
+    pub fn new(id: CachedPipelineId, generation: u64) -> Self {
+        CachedComputePipelineId(id, generation)
     }
 }
 
 #[derive(Error, Debug)]
 pub enum PipelineCacheError {
-    #[error(transparent)]
-    ProcessShaderError(#[from] naga_oil::compose::ComposerError),
+    #[error("{0}")]
+    ProcessShaderError(ShaderDiagnostic),
     #[error("Shader import not yet available.")]
     ShaderImportNotYetAvailable,
     #[error("Could not create shader module: {0}")]
-    CreateShaderModule(String),
+    CreateShaderModule(ShaderDiagnostic),
+    #[error("Pipeline layout does not match the shader: {0}")]
+    LayoutMismatch(String),
 }
 
 pub enum Pipeline {
     RenderPipeline(RenderPipeline),
+    ComputePipeline(ComputePipeline),
 }
 
 pub enum CachedPipelineState {
     Queued,
+    ///管线正在后台线程中创建
+    Creating,
     /// The pipeline GPU object was created successfully and is available (allocated on the GPU).
     Ok(Pipeline),
     Err(PipelineCacheError),
@@ -57,13 +108,96 @@ pub enum CachedPipelineState {
 
 pub enum PipelineDescriptor {
     RenderPipelineDescriptor(Box<RenderPipelineDescriptor>),
+    ComputePipelineDescriptor(Box<ComputePipelineDescriptor>),
 }
 
 pub struct CachedPipeline {
     pub descriptor: PipelineDescriptor,
     pub state: CachedPipelineState,
+    ///管线不可用时代替它的管线
+    pub fallback: Option<Pipeline>,
+    ///每次重新创建管线时更新,用于丢弃过期的后台创建结果
+    pub generation: u64,
+    ///注册此描述的次数,归零时管线被移除
+    pub ref_count: usize,
+    ///注册时分配,与id中的代数不一致时说明id指向的管线已被移除
+    pub id_generation: u64,
+    ///管线使用的绑定组布局,描述中未指定时由着色器反射得到
+    pub bind_group_layouts: Vec<BindGroupLayout>,
+    pub layout: Option<Arc<PipelineLayout>>,
+}
+
+///在渲染线程准备好的管线创建参数
+enum PipelineCreation {
+    Render {
+        vertex_module: Arc<ShaderModule>,
+        fragment_module: Option<Arc<ShaderModule>>,
+        bind_group_layouts: Vec<BindGroupLayout>,
+        layout: Option<Arc<PipelineLayout>>,
+        desc: RenderPipelineDescriptor,
+    },
+    Compute {
+        module: Arc<ShaderModule>,
+        bind_group_layouts: Vec<BindGroupLayout>,
+        layout: Option<Arc<PipelineLayout>>,
+        desc: ComputePipelineDescriptor,
+    },
 }
 
+impl PipelineCreation {
+    fn bind_group_layouts(&self) -> &[BindGroupLayout] {
+        match self {
+            PipelineCreation::Render {
+                bind_group_layouts, ..
+            }
+            | PipelineCreation::Compute {
+                bind_group_layouts, ..
+            } => bind_group_layouts,
+        }
+    }
+
+    fn layout(&self) -> Option<&Arc<PipelineLayout>> {
+        match self {
+            PipelineCreation::Render { layout, .. } | PipelineCreation::Compute { layout, .. } => {
+                layout.as_ref()
+            }
+        }
+    }
+
+    fn create(self, device: &Device) -> Pipeline {
+        match self {
+            PipelineCreation::Render {
+                vertex_module,
+                fragment_module,
+                layout,
+                desc,
+                ..
+            } => Pipeline::RenderPipeline(device.create_render_pipeline(
+                RenderPipelineDescriptorState {
+                    vertex_module: &vertex_module,
+                    fragment_module: fragment_module.as_deref(),
+                    layout: layout.as_deref(),
+                    desc,
+                },
+            )),
+            PipelineCreation::Compute {
+                module,
+                layout,
+                desc,
+                ..
+            } => Pipeline::ComputePipeline(device.create_compute_pipeline(
+                ComputePipelineDescriptorState {
+                    module: &module,
+                    layout: layout.as_deref(),
+                    desc,
+                },
+            )),
+        }
+    }
+}
+
+type PipelineCreationResult = (CachedPipelineId, u64, Pipeline);
+
 type LayoutCacheKey = (Vec<BindGroupLayoutId>, Vec<PushConstantRange>);
 
 #[derive(Default)]
@@ -72,15 +206,30 @@ struct LayoutCache {
 }
 
 impl LayoutCache {
+    fn key(
+        bind_group_layouts: &[BindGroupLayout],
+        push_constant_ranges: &[PushConstantRange],
+    ) -> LayoutCacheKey {
+        (
+            bind_group_layouts.iter().map(BindGroupLayout::id).collect(),
+            push_constant_ranges.to_vec(),
+        )
+    }
+
+    ///移除不再被任何管线使用的管线布局
+    fn collect(&mut self) {
+        self.layouts
+            .retain(|_, layout| Arc::strong_count(layout) > 1);
+    }
+
     fn get(
         &mut self,
         device: &Device,
         bind_group_layouts: &[BindGroupLayout],
         push_constant_ranges: Vec<PushConstantRange>,
     ) -> Arc<PipelineLayout> {
-        let bind_group_ids = bind_group_layouts.iter().map(BindGroupLayout::id).collect();
         self.layouts
-            .entry((bind_group_ids, push_constant_ranges))
+            .entry(Self::key(bind_group_layouts, &push_constant_ranges))
             .or_insert_with_key(|(_, push_constant_ranges)| {
                 let bind_group_layouts = bind_group_layouts
                     .iter()
@@ -98,36 +247,75 @@ impl LayoutCache {
 pub struct PipelineCache {
     shader_cache: ShaderCache,
     layout_cache: LayoutCache,
-    pipelines: Vec<CachedPipeline>,
+    ///已移除的管线留下空位
+    pipelines: Vec<Option<CachedPipeline>>,
     waiting_pipelines: HashSet<CachedPipelineId>,
-    new_pipelines: Vec<CachedPipeline>,
+    ///空位的id,注册新管线时复用
+    free_pipeline_ids: Vec<CachedPipelineId>,
+    next_generation: u64,
+    ///相同描述的渲染管线共享同一个id
+    render_pipeline_ids: HashMap<RenderPipelineDescriptor, CachedRenderPipelineId>,
+    compute_pipeline_ids: HashMap<ComputePipelineDescriptor, CachedComputePipelineId>,
+    fallback: PipelineFallback,
+    task_pool: Option<Arc<TaskPool>>,
+    created_sender: Sender<PipelineCreationResult>,
+    created_receiver: Receiver<PipelineCreationResult>,
+    ///磁盘管线缓存文件
+    disk_cache_path: Option<PathBuf>,
     pub device: Arc<Device>,
 }
 
+impl Drop for PipelineCache {
+    fn drop(&mut self) {
+        self.save_disk_cache();
+    }
+}
+
 impl PipelineCache {
     pub fn new(device: Arc<Device>) -> Self {
+        let (created_sender, created_receiver) = channel();
+
         Self {
-            shader_cache: ShaderCache::default(),
+            shader_cache: ShaderCache::new(&device),
             pipelines: Default::default(),
             waiting_pipelines: Default::default(),
-            new_pipelines: Default::default(),
+            free_pipeline_ids: Default::default(),
+            next_generation: 0,
+            render_pipeline_ids: Default::default(),
+            compute_pipeline_ids: Default::default(),
+            fallback: PipelineFallback::default(),
+            task_pool: None,
+            created_sender,
+            created_receiver,
+            disk_cache_path: None,
             device,
             layout_cache: Default::default(),
         }
     }
 
     pub fn process_queue(&mut self) {
-        let mut waiting_pipelines = mem::take(&mut self.waiting_pipelines);
+        let waiting_pipelines = mem::take(&mut self.waiting_pipelines);
         let mut pipelines = mem::take(&mut self.pipelines);
 
-        for new_pipeline in self.new_pipelines.drain(..) {
-            let id = pipelines.len();
-            pipelines.push(new_pipeline);
-            waiting_pipelines.insert(id);
+        while let Ok((id, generation, pipeline)) = self.created_receiver.try_recv() {
+            if let Some(cached_pipeline) =
+                pipelines
+                    .get_mut(id)
+                    .and_then(Option::as_mut)
+                    .filter(|cached_pipeline| {
+                        cached_pipeline.generation == generation
+                            && matches!(cached_pipeline.state, CachedPipelineState::Creating)
+                    })
+            {
+                cached_pipeline.state = CachedPipelineState::Ok(pipeline);
+                cached_pipeline.fallback = None;
+            }
         }
 
         for id in waiting_pipelines {
-            self.process_pipeline(&mut pipelines[id], id);
+            if let Some(cached_pipeline) = pipelines[id].as_mut() {
+                self.process_pipeline(cached_pipeline, id);
+            }
         }
 
         self.pipelines = pipelines;
@@ -136,24 +324,83 @@ impl PipelineCache {
     fn process_pipeline(&mut self, cached_pipeline: &mut CachedPipeline, id: usize) {
         match &mut cached_pipeline.state {
             CachedPipelineState::Queued => {
-                cached_pipeline.state = match &cached_pipeline.descriptor {
+                let creation = match &cached_pipeline.descriptor {
                     PipelineDescriptor::RenderPipelineDescriptor(descriptor) => {
                         self.start_create_render_pipeline(id, *descriptor.clone())
                     }
+                    PipelineDescriptor::ComputePipelineDescriptor(descriptor) => {
+                        self.start_create_compute_pipeline(id, *descriptor.clone())
+                    }
+                };
+
+                if let Ok(creation) = &creation {
+                    cached_pipeline.bind_group_layouts = creation.bind_group_layouts().to_vec();
+                    cached_pipeline.layout = creation.layout().cloned();
+                }
+
+                cached_pipeline.state = match creation {
+                    Ok(creation) => match &self.task_pool {
+                        Some(task_pool) => {
+                            let device = self.device.clone();
+                            let sender = self.created_sender.clone();
+                            let generation = cached_pipeline.generation;
+
+                            task_pool.spawn_task(async move {
+                                let pipeline = creation.create(&device);
+                                let _ = sender.send((id, generation, pipeline));
+                            });
+
+                            CachedPipelineState::Creating
+                        }
+                        None => CachedPipelineState::Ok(creation.create(&self.device)),
+                    },
+                    Err(err) => CachedPipelineState::Err(err),
                 };
+
+                match &cached_pipeline.state {
+                    CachedPipelineState::Ok(_) => cached_pipeline.fallback = None,
+                    CachedPipelineState::Err(PipelineCacheError::ShaderImportNotYetAvailable) => {}
+                    CachedPipelineState::Err(_) => {
+                        if self.fallback == PipelineFallback::ErrorPipeline {
+                            //没有解析过的布局时使用描述中指定的布局
+                            if cached_pipeline.layout.is_none() {
+                                let (bind_group_layouts, layout) =
+                                    self.error_pipeline_layout(&cached_pipeline.descriptor);
+                                cached_pipeline.bind_group_layouts = bind_group_layouts;
+                                cached_pipeline.layout = layout;
+                            }
+
+                            cached_pipeline.fallback = self.create_error_pipeline(
+                                &cached_pipeline.descriptor,
+                                cached_pipeline.layout.clone(),
+                            );
+                        }
+                    }
+                    CachedPipelineState::Queued | CachedPipelineState::Creating => {}
+                }
+
+                if matches!(cached_pipeline.state, CachedPipelineState::Creating) {
+                    return;
+                }
             }
 
+            CachedPipelineState::Creating => return,
+
             CachedPipelineState::Err(err) => match err {
                 PipelineCacheError::ShaderImportNotYetAvailable => {
                     cached_pipeline.state = CachedPipelineState::Queued;
                 }
 
-                PipelineCacheError::ProcessShaderError(_err) => {
-                    error!("failed to process shader:\n",);
+                PipelineCacheError::ProcessShaderError(diagnostic) => {
+                    error!("failed to process shader:\n{}", diagnostic);
                     return;
                 }
-                PipelineCacheError::CreateShaderModule(description) => {
-                    error!("failed to create shader module: {}", description);
+                PipelineCacheError::CreateShaderModule(diagnostic) => {
+                    error!("failed to create shader module:\n{}", diagnostic);
+                    return;
+                }
+                PipelineCacheError::LayoutMismatch(message) => {
+                    error!("pipeline layout does not match the shader: {}", message);
                     return;
                 }
             },
@@ -165,53 +412,318 @@ impl PipelineCache {
         self.waiting_pipelines.insert(id);
     }
 
-    fn start_create_render_pipeline(
+    ///错误管线使用与原管线相同的布局,调用者绑定的绑定组仍然兼容
+    fn create_error_pipeline(
+        &self,
+        descriptor: &PipelineDescriptor,
+        layout: Option<Arc<PipelineLayout>>,
+    ) -> Option<Pipeline> {
+        let PipelineDescriptor::RenderPipelineDescriptor(descriptor) = descriptor else {
+            return None;
+        };
+
+        let module = match self.device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("error_shader".into()),
+            source: ShaderModuleSource::Wgsl(error_shader_source(descriptor).into()),
+        }) {
+            Ok(module) => module,
+            Err(err) => {
+                error!("failed to create error shader: {}", err);
+                return None;
+            }
+        };
+
+        let mut desc = *descriptor.clone();
+        desc.label = desc.label.map(|label| format!("{}_error", label).into());
+        desc.vertex.entry_point = ERROR_VERTEX_ENTRY_POINT.into();
+        if let Some(fragment) = desc.fragment.as_mut() {
+            fragment.entry_point = ERROR_FRAGMENT_ENTRY_POINT.into();
+        }
+
+        let fragment_module = desc.fragment.as_ref().map(|_| &module);
+
+        let pipeline = self
+            .device
+            .create_render_pipeline(RenderPipelineDescriptorState {
+                vertex_module: &module,
+                fragment_module,
+                layout: layout.as_deref(),
+                desc,
+            });
+
+        Some(Pipeline::RenderPipeline(pipeline))
+    }
+
+    fn error_pipeline_layout(
         &mut self,
-        id: CachedPipelineId,
-        descriptor: RenderPipelineDescriptor,
-    ) -> CachedPipelineState {
-        let vertex_module = match self
-            .shader_cache
-            .get(&self.device, id, &descriptor.vertex.shader)
-        {
-            Some(module) => module,
-            None => {
-                return CachedPipelineState::Err(PipelineCacheError::ShaderImportNotYetAvailable);
+        descriptor: &PipelineDescriptor,
+    ) -> (Vec<BindGroupLayout>, Option<Arc<PipelineLayout>>) {
+        let (layout, push_constant_ranges) = match descriptor {
+            PipelineDescriptor::RenderPipelineDescriptor(desc) => {
+                (&desc.layout, &desc.push_constant_ranges)
+            }
+            PipelineDescriptor::ComputePipelineDescriptor(desc) => {
+                (&desc.layout, &desc.push_constant_ranges)
             }
         };
 
-        let fragment_module = match &descriptor.fragment {
-            Some(fragment) => match self.shader_cache.get(&self.device, id, &fragment.shader) {
-                Some(module) => Some(module),
-                None => {
-                    return CachedPipelineState::Err(
-                        PipelineCacheError::ShaderImportNotYetAvailable,
-                    );
-                }
-            },
-            None => None,
+        let mut bind_group_layouts = layout.to_vec();
+
+        let push_constant_ranges = if self
+            .device
+            .features()
+            .contains(wgpu::Features::PUSH_CONSTANTS)
+        {
+            push_constant_ranges.to_vec()
+        } else {
+            if !push_constant_ranges.is_empty()
+                && self
+                    .add_push_constant_fallback_layout(
+                        &mut bind_group_layouts,
+                        push_constant_ranges,
+                    )
+                    .is_err()
+            {
+                bind_group_layouts = layout.to_vec();
+            }
+            vec![]
         };
 
-        let layout = if descriptor.layout.is_empty() && descriptor.push_constant_ranges.is_empty() {
+        let layout = if bind_group_layouts.is_empty() && push_constant_ranges.is_empty() {
             None
         } else {
-            Some(self.layout_cache.get(
-                &self.device,
-                &descriptor.layout,
-                descriptor.push_constant_ranges.to_vec(),
-            ))
+            Some(
+                self.layout_cache
+                    .get(&self.device, &bind_group_layouts, push_constant_ranges),
+            )
         };
 
-        let pipeline = self
+        (bind_group_layouts, layout)
+    }
+
+    ///描述中未指定的布局、顶点缓冲区和推送常量由着色器反射得到,指定的则与着色器校验
+    fn resolve_layout(
+        &mut self,
+        reflection: &PipelineReflection,
+        layout: &[BindGroupLayout],
+        push_constant_ranges: &[PushConstantRange],
+    ) -> Result<(Vec<BindGroupLayout>, Option<Arc<PipelineLayout>>), PipelineCacheError> {
+        let supports_push_constants = self
             .device
-            .create_render_pipeline(RenderPipelineDescriptorState {
-                vertex_module: &vertex_module,
-                fragment_module: fragment_module.as_deref(),
-                layout: layout.as_deref(),
-                desc: descriptor,
+            .features()
+            .contains(wgpu::Features::PUSH_CONSTANTS);
+
+        let bind_group_layouts = if layout.is_empty() {
+            let mut infos = reflection.bind_group_layout_infos();
+
+            if !supports_push_constants {
+                Self::apply_push_constant_fallback(&mut infos)?;
+            }
+
+            infos
+                .into_iter()
+                .map(|info| self.device.create_bind_group_layout(info))
+                .collect::<Vec<_>>()
+        } else {
+            let mut layout = layout.to_vec();
+
+            if !supports_push_constants && !push_constant_ranges.is_empty() {
+                self.add_push_constant_fallback_layout(&mut layout, push_constant_ranges)?;
+            }
+
+            reflection
+                .validate_bind_group_layouts(&layout)
+                .map_err(PipelineCacheError::LayoutMismatch)?;
+            layout
+        };
+
+        let push_constant_ranges = if !supports_push_constants {
+            vec![]
+        } else if push_constant_ranges.is_empty() {
+            reflection.push_constant_ranges.clone()
+        } else {
+            reflection
+                .validate_push_constant_ranges(push_constant_ranges)
+                .map_err(PipelineCacheError::LayoutMismatch)?;
+            push_constant_ranges.to_vec()
+        };
+
+        let layout = if bind_group_layouts.is_empty() && push_constant_ranges.is_empty() {
+            None
+        } else {
+            Some(
+                self.layout_cache
+                    .get(&self.device, &bind_group_layouts, push_constant_ranges),
+            )
+        };
+
+        Ok((bind_group_layouts, layout))
+    }
+
+    ///反射得到的回退组改为带动态偏移的uniform缓冲区,与绑定推送常量数据的方式一致
+    fn apply_push_constant_fallback(
+        infos: &mut [BindGroupLayoutInfo],
+    ) -> Result<(), PipelineCacheError> {
+        let group = PUSH_CONSTANT_FALLBACK_GROUP as usize;
+
+        let Some(info) = infos.get_mut(group) else {
+            return Ok(());
+        };
+
+        for entry in info.entries.iter_mut() {
+            match &mut entry.ty {
+                wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset,
+                    ..
+                } if entry.binding == 0 => *has_dynamic_offset = true,
+                _ => {
+                    return Err(PipelineCacheError::LayoutMismatch(format!(
+                        "@group({}) is reserved for the push constant fallback",
+                        group
+                    )));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    ///用空布局补齐到回退组号,在回退组放入承载推送常量的带动态偏移的uniform缓冲区
+    fn add_push_constant_fallback_layout(
+        &self,
+        layout: &mut Vec<BindGroupLayout>,
+        push_constant_ranges: &[PushConstantRange],
+    ) -> Result<(), PipelineCacheError> {
+        let group = PUSH_CONSTANT_FALLBACK_GROUP as usize;
+
+        if layout.len() > group {
+            return Err(PipelineCacheError::LayoutMismatch(format!(
+                "@group({}) is reserved for the push constant fallback",
+                group
+            )));
+        }
+
+        layout.resize_with(group, || {
+            self.device.create_bind_group_layout(BindGroupLayoutInfo {
+                label: Some("empty_bind_group_layout".into()),
+                entries: vec![],
+            })
+        });
+
+        let visibility = push_constant_ranges
+            .iter()
+            .fold(wgpu::ShaderStages::NONE, |visibility, range| {
+                visibility | range.stages
             });
 
-        CachedPipelineState::Ok(Pipeline::RenderPipeline(pipeline))
+        layout.push(self.device.create_bind_group_layout(BindGroupLayoutInfo {
+            label: Some("push_constant_fallback_layout".into()),
+            entries: vec![wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        }));
+
+        Ok(())
+    }
+
+    fn start_create_render_pipeline(
+        &mut self,
+        id: CachedPipelineId,
+        mut descriptor: RenderPipelineDescriptor,
+    ) -> Result<PipelineCreation, PipelineCacheError> {
+        let vertex = self.shader_cache.get(
+            &self.device,
+            id,
+            &descriptor.vertex.shader,
+            &descriptor.vertex.shader_defs,
+        )?;
+
+        let fragment = match &descriptor.fragment {
+            Some(fragment) => Some(self.shader_cache.get(
+                &self.device,
+                id,
+                &fragment.shader,
+                &fragment.shader_defs,
+            )?),
+            None => None,
+        };
+
+        let reflection = PipelineReflection::new(
+            vertex
+                .reflection
+                .entry_point(&descriptor.vertex.entry_point)
+                .into_iter()
+                .chain(
+                    fragment
+                        .as_ref()
+                        .zip(descriptor.fragment.as_ref())
+                        .and_then(|(fragment, state)| {
+                            fragment.reflection.entry_point(&state.entry_point)
+                        }),
+                ),
+        );
+
+        if descriptor.vertex.buffers.is_empty() {
+            descriptor
+                .vertex
+                .buffers
+                .extend(reflection.vertex_buffer_layout());
+        } else {
+            reflection
+                .validate_vertex_buffers(&descriptor.vertex.buffers)
+                .map_err(PipelineCacheError::LayoutMismatch)?;
+        }
+
+        let (bind_group_layouts, layout) = self.resolve_layout(
+            &reflection,
+            &descriptor.layout,
+            &descriptor.push_constant_ranges,
+        )?;
+
+        Ok(PipelineCreation::Render {
+            vertex_module: vertex.module,
+            fragment_module: fragment.map(|fragment| fragment.module),
+            bind_group_layouts,
+            layout,
+            desc: descriptor,
+        })
+    }
+
+    fn start_create_compute_pipeline(
+        &mut self,
+        id: CachedPipelineId,
+        descriptor: ComputePipelineDescriptor,
+    ) -> Result<PipelineCreation, PipelineCacheError> {
+        let shader = self.shader_cache.get(
+            &self.device,
+            id,
+            &descriptor.shader,
+            &descriptor.shader_defs,
+        )?;
+
+        let reflection =
+            PipelineReflection::new(shader.reflection.entry_point(&descriptor.entry_point));
+
+        let (bind_group_layouts, layout) = self.resolve_layout(
+            &reflection,
+            &descriptor.layout,
+            &descriptor.push_constant_ranges,
+        )?;
+
+        Ok(PipelineCreation::Compute {
+            module: shader.module,
+            bind_group_layouts,
+            layout,
+            desc: descriptor,
+        })
     }
 
     pub fn update(&mut self, dt: f32) {
@@ -231,21 +743,125 @@ impl PipelineCache {
         self.free(free_pipeline_ids);
     }
 
+    ///着色器编译失败时get_render_pipeline和get_compute_pipeline的返回值
+    pub fn set_fallback(&mut self, fallback: PipelineFallback) {
+        self.fallback = fallback;
+    }
+
+    pub fn fallback(&self) -> PipelineFallback {
+        self.fallback
+    }
+
+    ///从目录中加载磁盘管线缓存,退出时保存到同一目录,需要在注册管线之前调用
+    pub fn set_disk_cache_directory(&mut self, directory: impl AsRef<Path>) {
+        let Some(key) = self.device.pipeline_cache_key() else {
+            return;
+        };
+
+        let path = directory.as_ref().join(format!("{}.bin", key));
+        let data = fs::read(&path).ok();
+
+        self.device.init_pipeline_cache(data.as_deref());
+        self.disk_cache_path = Some(path);
+    }
+
+    pub fn save_disk_cache(&self) {
+        let Some(path) = self.disk_cache_path.as_ref() else {
+            return;
+        };
+
+        let Some(data) = self.device.pipeline_cache_data() else {
+            return;
+        };
+
+        //先写入临时文件,避免保存中断时留下损坏的缓存
+        let temp_path = path.with_extension("tmp");
+
+        let result = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&temp_path, &data))
+            .and_then(|_| fs::rename(&temp_path, path));
+
+        if let Err(e) = result {
+            warn!("failed to save pipeline cache {}: {}", path.display(), e);
+        }
+    }
+
+    ///设置后管线在任务池中创建,渲染线程不会等待管线编译
+    pub fn set_task_pool(&mut self, task_pool: Option<Arc<TaskPool>>) {
+        self.task_pool = task_pool;
+    }
+
     pub fn free(&mut self, ids: Vec<CachedPipelineId>) {
-        for cached_pipeline in ids {
-            self.pipelines[cached_pipeline].state = CachedPipelineState::Queued;
-            self.waiting_pipelines.insert(cached_pipeline);
+        for id in ids {
+            let Some(cached_pipeline) = self.pipelines.get_mut(id).and_then(Option::as_mut) else {
+                continue;
+            };
+            self.next_generation += 1;
+            cached_pipeline.generation = self.next_generation;
+            let state = mem::replace(&mut cached_pipeline.state, CachedPipelineState::Queued);
+
+            match state {
+                CachedPipelineState::Ok(pipeline)
+                    if self.fallback == PipelineFallback::LastGood =>
+                {
+                    cached_pipeline.fallback = Some(pipeline);
+                }
+                _ => {}
+            }
+
+            self.waiting_pipelines.insert(id);
         }
     }
 
-    pub fn get_render_pipeline(&self, id: &CachedRenderPipelineId) -> Option<&RenderPipeline> {
-        if id.0 >= self.pipelines.len() {
-            return None;
+    fn get_cached_pipeline(
+        &self,
+        id: CachedPipelineId,
+        generation: u64,
+    ) -> Option<&CachedPipeline> {
+        self.pipelines
+            .get(id)?
+            .as_ref()
+            .filter(|cached_pipeline| cached_pipeline.id_generation == generation)
+    }
+
+    fn get_pipeline(&self, id: CachedPipelineId, generation: u64) -> Option<&Pipeline> {
+        let cached_pipeline = self.get_cached_pipeline(id, generation)?;
+
+        match &cached_pipeline.state {
+            CachedPipelineState::Ok(pipeline) => Some(pipeline),
+            _ => cached_pipeline.fallback.as_ref(),
         }
+    }
 
-        if let CachedPipelineState::Ok(Pipeline::RenderPipeline(pipeline)) =
-            &self.pipelines[id.0].state
-        {
+    pub fn get_pipeline_state(&self, id: CachedPipelineId) -> Option<&CachedPipelineState> {
+        self.pipelines
+            .get(id)?
+            .as_ref()
+            .map(|pipeline| &pipeline.state)
+    }
+
+    ///创建失败的管线,供编辑器展示着色器错误
+    pub fn pipeline_errors(
+        &self,
+    ) -> impl Iterator<Item = (CachedPipelineId, &PipelineCacheError)> + '_ {
+        self.pipelines
+            .iter()
+            .enumerate()
+            .filter_map(
+                |(id, pipeline)| match pipeline.as_ref().map(|pipeline| &pipeline.state) {
+                    Some(CachedPipelineState::Err(
+                        PipelineCacheError::ShaderImportNotYetAvailable,
+                    )) => None,
+                    Some(CachedPipelineState::Err(err)) => Some((id, err)),
+                    _ => None,
+                },
+            )
+    }
+
+    pub fn get_render_pipeline(&self, id: &CachedRenderPipelineId) -> Option<&RenderPipeline> {
+        if let Some(Pipeline::RenderPipeline(pipeline)) = self.get_pipeline(id.0, id.1) {
             Some(pipeline)
         } else {
             None
@@ -256,11 +872,414 @@ impl PipelineCache {
         &mut self,
         desc: RenderPipelineDescriptor,
     ) -> CachedRenderPipelineId {
-        let id = CachedRenderPipelineId::new(self.pipelines.len() + self.new_pipelines.len());
-        self.new_pipelines.push(CachedPipeline {
-            descriptor: PipelineDescriptor::RenderPipelineDescriptor(Box::new(desc)),
-            state: CachedPipelineState::Queued,
-        });
+        if let Some(id) = self.render_pipeline_ids.get(&desc).copied() {
+            self.retain_pipeline(id.0);
+            return id;
+        }
+
+        let (id, generation) = self.insert_pipeline(PipelineDescriptor::RenderPipelineDescriptor(
+            Box::new(desc.clone()),
+        ));
+        let id = CachedRenderPipelineId::new(id, generation);
+        self.render_pipeline_ids.insert(desc, id);
+        id
+    }
+
+    ///每次注册对应一次移除,最后一次移除时释放管线
+    pub fn remove_render_pipeline(&mut self, id: CachedRenderPipelineId) {
+        self.release_pipeline(id.0, id.1);
+    }
+
+    ///管线创建后可用,描述中未指定布局时为反射得到的布局
+    pub fn get_render_pipeline_bind_group_layout(
+        &self,
+        id: &CachedRenderPipelineId,
+        index: usize,
+    ) -> Option<&BindGroupLayout> {
+        self.get_bind_group_layout(id.0, id.1, index)
+    }
+
+    pub fn get_compute_pipeline(&self, id: &CachedComputePipelineId) -> Option<&ComputePipeline> {
+        if let Some(Pipeline::ComputePipeline(pipeline)) = self.get_pipeline(id.0, id.1) {
+            Some(pipeline)
+        } else {
+            None
+        }
+    }
+
+    pub fn register_compute_pipeline(
+        &mut self,
+        desc: ComputePipelineDescriptor,
+    ) -> CachedComputePipelineId {
+        if let Some(id) = self.compute_pipeline_ids.get(&desc).copied() {
+            self.retain_pipeline(id.0);
+            return id;
+        }
+
+        let (id, generation) = self.insert_pipeline(PipelineDescriptor::ComputePipelineDescriptor(
+            Box::new(desc.clone()),
+        ));
+        let id = CachedComputePipelineId::new(id, generation);
+        self.compute_pipeline_ids.insert(desc, id);
         id
     }
+
+    pub fn remove_compute_pipeline(&mut self, id: CachedComputePipelineId) {
+        self.release_pipeline(id.0, id.1);
+    }
+
+    pub fn get_compute_pipeline_bind_group_layout(
+        &self,
+        id: &CachedComputePipelineId,
+        index: usize,
+    ) -> Option<&BindGroupLayout> {
+        self.get_bind_group_layout(id.0, id.1, index)
+    }
+
+    fn get_bind_group_layout(
+        &self,
+        id: CachedPipelineId,
+        generation: u64,
+        index: usize,
+    ) -> Option<&BindGroupLayout> {
+        self.get_cached_pipeline(id, generation)?
+            .bind_group_layouts
+            .get(index)
+    }
+
+    ///返回管线槽位和注册时的代数
+    fn insert_pipeline(&mut self, descriptor: PipelineDescriptor) -> (CachedPipelineId, u64) {
+        self.next_generation += 1;
+
+        let cached_pipeline = CachedPipeline {
+            descriptor,
+            state: CachedPipelineState::Queued,
+            fallback: None,
+            generation: self.next_generation,
+            ref_count: 1,
+            id_generation: self.next_generation,
+            bind_group_layouts: vec![],
+            layout: None,
+        };
+
+        let id = match self.free_pipeline_ids.pop() {
+            Some(id) => {
+                self.pipelines[id] = Some(cached_pipeline);
+                id
+            }
+            None => {
+                self.pipelines.push(Some(cached_pipeline));
+                self.pipelines.len() - 1
+            }
+        };
+
+        self.waiting_pipelines.insert(id);
+        (id, self.next_generation)
+    }
+
+    fn retain_pipeline(&mut self, id: CachedPipelineId) {
+        if let Some(cached_pipeline) = self.pipelines.get_mut(id).and_then(Option::as_mut) {
+            cached_pipeline.ref_count += 1;
+        }
+    }
+
+    ///id已被移除时忽略,不会影响复用此槽位的管线
+    fn release_pipeline(&mut self, id: CachedPipelineId, generation: u64) {
+        let cached_pipeline = match self.pipelines.get_mut(id) {
+            Some(Some(cached_pipeline)) if cached_pipeline.id_generation != generation => return,
+            Some(Some(cached_pipeline)) if cached_pipeline.ref_count > 1 => {
+                cached_pipeline.ref_count -= 1;
+                return;
+            }
+            Some(slot) => slot.take(),
+            None => None,
+        };
+
+        let Some(cached_pipeline) = cached_pipeline else {
+            return;
+        };
+
+        match &cached_pipeline.descriptor {
+            PipelineDescriptor::RenderPipelineDescriptor(descriptor) => {
+                self.render_pipeline_ids.remove(descriptor.as_ref());
+            }
+            PipelineDescriptor::ComputePipelineDescriptor(descriptor) => {
+                self.compute_pipeline_ids.remove(descriptor.as_ref());
+            }
+        }
+
+        self.waiting_pipelines.remove(&id);
+        self.shader_cache.remove_pipeline(id);
+        self.free_pipeline_ids.push(id);
+
+        //先释放管线持有的布局,再回收只被缓存持有的布局
+        drop(cached_pipeline);
+        self.layout_cache.collect();
+        self.device.collect_bind_group_layouts();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{
+        build_in::get_test,
+        error::RendererError,
+        gfx_base::{
+            BindGroup, BindGroupInfo, BindGroupLayout, BindGroupLayoutInfo, Buffer, BufferInfo,
+            BufferInitInfo, ComputePipeline, ComputePipelineDescriptor,
+            ComputePipelineDescriptorState, PipelineLayout, PipelineLayoutDescriptor, QuerySet,
+            QuerySetInfo, RenderPass, RenderPassDescriptor, RenderPipeline,
+            RenderPipelineDescriptorState, Sample, SampleInfo, ShaderModule,
+            ShaderModuleDescriptor, ShaderReflection, Texture, TextureInfo,
+            command_buffer::{CommandBuffer, CommandBufferInfo},
+            device::{Device, DeviceTrait, QueueType},
+            shader::{Shader, ShaderDefinition, ShaderResource},
+        },
+    };
+
+    use super::{
+        PUSH_CONSTANT_FALLBACK_GROUP, PipelineCache, PipelineCacheError, PipelineReflection,
+    };
+
+    ///只用于管线注册,不会真正创建管线
+    #[derive(Debug)]
+    struct TestDevice;
+
+    impl DeviceTrait for TestDevice {
+        fn create_bind_group_layout(&self, _desc: BindGroupLayoutInfo) -> BindGroupLayout {
+            unimplemented!()
+        }
+
+        fn collect_bind_group_layouts(&self) {}
+
+        fn create_render_pass(&self, _desc: RenderPassDescriptor) -> RenderPass {
+            unimplemented!()
+        }
+
+        fn create_render_pipeline(&self, _desc: RenderPipelineDescriptorState) -> RenderPipeline {
+            unimplemented!()
+        }
+
+        fn create_compute_pipeline(
+            &self,
+            _desc: ComputePipelineDescriptorState,
+        ) -> ComputePipeline {
+            unimplemented!()
+        }
+
+        fn create_command_buffer(&self, _desc: CommandBufferInfo) -> CommandBuffer {
+            unimplemented!()
+        }
+
+        fn create_shader_module(
+            &self,
+            _desc: ShaderModuleDescriptor,
+        ) -> Result<ShaderModule, RendererError> {
+            unimplemented!()
+        }
+
+        fn create_pipeline_layout(&self, _desc: PipelineLayoutDescriptor) -> PipelineLayout {
+            unimplemented!()
+        }
+
+        fn create_buffer(&self, _desc: BufferInfo) -> Buffer {
+            unimplemented!()
+        }
+
+        fn create_texture(&self, _desc: TextureInfo) -> Texture {
+            unimplemented!()
+        }
+
+        fn has_queue(&self, queue_type: QueueType) -> bool {
+            queue_type == QueueType::Graphics
+        }
+
+        fn submit(&self, _queue_type: QueueType, _command_buffers: Vec<CommandBuffer>) {
+            unimplemented!()
+        }
+
+        fn create_buffer_init(&self, _desc: BufferInitInfo) -> Buffer {
+            unimplemented!()
+        }
+
+        fn write_buffer(&self, _buffer: &Buffer, _offset: wgpu::BufferAddress, _data: &[u8]) {
+            unimplemented!()
+        }
+
+        fn create_bind_group(&self, _desc: BindGroupInfo) -> BindGroup {
+            unimplemented!()
+        }
+
+        fn create_sampler(&self, _desc: SampleInfo) -> Sample {
+            unimplemented!()
+        }
+
+        fn create_query_set(&self, _desc: QuerySetInfo) -> QuerySet {
+            unimplemented!()
+        }
+
+        fn features(&self) -> wgpu::Features {
+            wgpu::Features::empty()
+        }
+
+        fn timestamp_period(&self) -> f32 {
+            1.0
+        }
+
+        fn pipeline_cache_key(&self) -> Option<String> {
+            None
+        }
+
+        fn init_pipeline_cache(&self, _data: Option<&[u8]>) {}
+
+        fn pipeline_cache_data(&self) -> Option<Vec<u8>> {
+            None
+        }
+    }
+
+    fn pipeline_cache() -> PipelineCache {
+        PipelineCache::new(Arc::new(Device::new(TestDevice)))
+    }
+
+    fn descriptor(entry_point: &'static str) -> ComputePipelineDescriptor {
+        ComputePipelineDescriptor {
+            label: None,
+            layout: vec![],
+            push_constant_ranges: vec![],
+            shader: get_test().clone(),
+            shader_defs: vec![],
+            entry_point: entry_point.into(),
+        }
+    }
+
+    #[test]
+    fn register_same_descriptor() {
+        let mut cache = pipeline_cache();
+
+        let a = cache.register_compute_pipeline(descriptor("main"));
+        let b = cache.register_compute_pipeline(descriptor("main"));
+        let c = cache.register_compute_pipeline(descriptor("other"));
+
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(cache.pipelines.len(), 2);
+        assert_eq!(cache.pipelines[a.id()].as_ref().unwrap().ref_count, 2);
+    }
+
+    #[test]
+    fn remove_after_last_release() {
+        let mut cache = pipeline_cache();
+
+        let a = cache.register_compute_pipeline(descriptor("main"));
+        cache.register_compute_pipeline(descriptor("main"));
+
+        cache.remove_compute_pipeline(a);
+        assert_eq!(cache.pipelines[a.id()].as_ref().unwrap().ref_count, 1);
+
+        cache.remove_compute_pipeline(a);
+        assert!(cache.pipelines[a.id()].is_none());
+        assert!(!cache.waiting_pipelines.contains(&a.id()));
+        assert!(cache.compute_pipeline_ids.is_empty());
+
+        //多余的移除被忽略
+        cache.remove_compute_pipeline(a);
+        assert_eq!(cache.free_pipeline_ids, vec![a.id()]);
+    }
+
+    #[test]
+    fn reuse_released_id() {
+        let mut cache = pipeline_cache();
+
+        let a = cache.register_compute_pipeline(descriptor("main"));
+        let b = cache.register_compute_pipeline(descriptor("other"));
+        cache.remove_compute_pipeline(a);
+
+        let c = cache.register_compute_pipeline(descriptor("third"));
+
+        assert_eq!(c.id(), a.id());
+        assert_ne!(c, b);
+        assert_eq!(cache.pipelines.len(), 2);
+        assert!(cache.free_pipeline_ids.is_empty());
+        assert_eq!(cache.pipelines[c.id()].as_ref().unwrap().ref_count, 1);
+
+        //释放后仍被持有的id不会指向复用槽位的管线
+        assert_ne!(c, a);
+        assert!(cache.get_cached_pipeline(a.id(), a.generation()).is_none());
+        assert!(cache.get_cached_pipeline(c.id(), c.generation()).is_some());
+
+        cache.remove_compute_pipeline(a);
+        assert_eq!(cache.pipelines[c.id()].as_ref().unwrap().ref_count, 1);
+
+        //重新注册被释放的描述得到新的管线
+        let d = cache.register_compute_pipeline(descriptor("main"));
+        assert_eq!(d.id(), 2);
+    }
+
+    #[test]
+    fn validation_error_has_location() {
+        let mut cache = pipeline_cache();
+        let shader = ShaderResource::new_ok(
+            "invalid".into(),
+            Shader {
+                definition: ShaderDefinition::from_wgsl(
+                    "//缺少绑定\nvar<storage, read_write> data: array<f32>;\n\n@compute @workgroup_size(1)\nfn main() {\n    data[0] = 1.0;\n}\n",
+                    "invalid.wgsl",
+                ),
+                cache_index: Default::default(),
+            },
+        );
+
+        let result = cache.shader_cache.get(&cache.device, 0, &shader, &[]);
+
+        let Err(PipelineCacheError::ProcessShaderError(diagnostic)) = result else {
+            panic!("shader should fail validation");
+        };
+        assert_eq!(diagnostic.path, "invalid.wgsl");
+        assert_eq!(diagnostic.line, Some(2));
+    }
+
+    fn reflected_layout_infos(source: &str) -> Vec<BindGroupLayoutInfo> {
+        let module = naga::front::wgsl::parse_str(source).unwrap();
+        let info = naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::empty(),
+        )
+        .validate(&module)
+        .unwrap();
+        let reflection = ShaderReflection::new(&module, &info);
+
+        PipelineReflection::new(reflection.entry_point("main")).bind_group_layout_infos()
+    }
+
+    #[test]
+    fn reflected_push_constant_fallback_uses_dynamic_offset() {
+        let mut infos = reflected_layout_infos(
+            "struct PushConstants {\n    value: vec4f,\n}\n\n@group(3) @binding(0)\nvar<uniform> push_constants: PushConstants;\n\n@compute @workgroup_size(1)\nfn main() {\n    let value = push_constants.value;\n}\n",
+        );
+
+        PipelineCache::apply_push_constant_fallback(&mut infos).unwrap();
+
+        assert!(matches!(
+            infos[PUSH_CONSTANT_FALLBACK_GROUP as usize].entries[0].ty,
+            wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: true,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn reflected_push_constant_fallback_group_is_reserved() {
+        let mut infos = reflected_layout_infos(
+            "@group(3) @binding(0)\nvar<storage, read_write> data: array<f32>;\n\n@compute @workgroup_size(1)\nfn main() {\n    data[0] = 1.0;\n}\n",
+        );
+
+        assert!(matches!(
+            PipelineCache::apply_push_constant_fallback(&mut infos),
+            Err(PipelineCacheError::LayoutMismatch(_))
+        ));
+    }
 }
@@ -0,0 +1,579 @@
+use std::{collections::BTreeMap, num::NonZeroU32};
+
+use naga::{
+    AddressSpace, ArraySize, Binding, ImageClass, ImageDimension, ScalarKind, ShaderStage,
+    StorageAccess, StorageFormat, TypeInner, valid::ModuleInfo,
+};
+use wgpu::{
+    BindGroupLayoutEntry, BindingType, BufferBindingType, PushConstantRange, SamplerBindingType,
+    ShaderStages, StorageTextureAccess, TextureFormat, TextureSampleType, TextureViewDimension,
+    VertexAttribute, VertexFormat, VertexStepMode,
+};
+
+use crate::gfx_base::bind_group_layout::{BindGroupLayout, BindGroupLayoutInfo};
+
+use super::VertexBufferLayout;
+
+///着色器入口使用的资源绑定
+#[derive(Debug, Clone)]
+pub struct ReflectedBinding {
+    pub group: u32,
+    pub binding: u32,
+    pub ty: BindingType,
+    pub count: Option<NonZeroU32>,
+}
+
+///顶点着色器的输入
+#[derive(Debug, Clone, Copy)]
+pub struct ReflectedVertexInput {
+    pub location: u32,
+    pub kind: ScalarKind,
+    ///无法用顶点格式表示时为空
+    pub format: Option<VertexFormat>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EntryPointReflection {
+    pub name: String,
+    pub stage: ShaderStages,
+    pub bindings: Vec<ReflectedBinding>,
+    pub vertex_inputs: Vec<ReflectedVertexInput>,
+    ///推送常量的字节大小
+    pub push_constant_size: Option<u32>,
+}
+
+///从组合后的naga模块中反射出的入口信息
+#[derive(Debug, Clone, Default)]
+pub struct ShaderReflection {
+    entry_points: Vec<EntryPointReflection>,
+}
+
+impl ShaderReflection {
+    pub fn new(module: &naga::Module, info: &ModuleInfo) -> Self {
+        let entry_points = module
+            .entry_points
+            .iter()
+            .enumerate()
+            .map(|(index, entry_point)| {
+                let function_info = info.get_entry_point(index);
+
+                let mut bindings = vec![];
+                let mut push_constant_size = None;
+
+                for (handle, var) in module.global_variables.iter() {
+                    if function_info[handle].is_empty() {
+                        continue;
+                    }
+
+                    if var.space == AddressSpace::PushConstant {
+                        push_constant_size = Some(module.types[var.ty].inner.size(module.to_ctx()));
+                        continue;
+                    }
+
+                    let Some(resource_binding) = &var.binding else {
+                        continue;
+                    };
+
+                    if let Some((ty, count)) = binding_type(module, var) {
+                        bindings.push(ReflectedBinding {
+                            group: resource_binding.group,
+                            binding: resource_binding.binding,
+                            ty,
+                            count,
+                        });
+                    }
+                }
+
+                let vertex_inputs = if entry_point.stage == ShaderStage::Vertex {
+                    vertex_inputs(module, &entry_point.function)
+                } else {
+                    vec![]
+                };
+
+                EntryPointReflection {
+                    name: entry_point.name.clone(),
+                    stage: match entry_point.stage {
+                        ShaderStage::Vertex => ShaderStages::VERTEX,
+                        ShaderStage::Fragment => ShaderStages::FRAGMENT,
+                        ShaderStage::Compute => ShaderStages::COMPUTE,
+                    },
+                    bindings,
+                    vertex_inputs,
+                    push_constant_size,
+                }
+            })
+            .collect();
+
+        Self { entry_points }
+    }
+
+    pub fn entry_point(&self, name: &str) -> Option<&EntryPointReflection> {
+        self.entry_points
+            .iter()
+            .find(|entry_point| entry_point.name == name)
+    }
+}
+
+///管线所有阶段合并后的反射信息
+#[derive(Debug, Clone, Default)]
+pub struct PipelineReflection {
+    ///按组号排列的绑定,可见性为所有使用它的阶段
+    pub bind_groups: BTreeMap<u32, Vec<BindGroupLayoutEntry>>,
+    pub push_constant_ranges: Vec<PushConstantRange>,
+    pub vertex_inputs: Vec<ReflectedVertexInput>,
+}
+
+impl PipelineReflection {
+    pub fn new<'a>(entry_points: impl IntoIterator<Item = &'a EntryPointReflection>) -> Self {
+        let mut reflection = PipelineReflection::default();
+        let mut push_constant_range: Option<PushConstantRange> = None;
+
+        for entry_point in entry_points {
+            for binding in entry_point.bindings.iter() {
+                let entries = reflection.bind_groups.entry(binding.group).or_default();
+
+                match entries
+                    .iter_mut()
+                    .find(|entry| entry.binding == binding.binding)
+                {
+                    Some(entry) => entry.visibility |= entry_point.stage,
+                    None => entries.push(BindGroupLayoutEntry {
+                        binding: binding.binding,
+                        visibility: entry_point.stage,
+                        ty: binding.ty,
+                        count: binding.count,
+                    }),
+                }
+            }
+
+            if let Some(size) = entry_point.push_constant_size {
+                let range = push_constant_range.get_or_insert(PushConstantRange {
+                    stages: ShaderStages::NONE,
+                    range: 0..0,
+                });
+                range.stages |= entry_point.stage;
+                range.range.end = range.range.end.max(size);
+            }
+
+            reflection
+                .vertex_inputs
+                .extend(entry_point.vertex_inputs.iter().copied());
+        }
+
+        for entries in reflection.bind_groups.values_mut() {
+            entries.sort_by_key(|entry| entry.binding);
+        }
+
+        reflection.vertex_inputs.sort_by_key(|input| input.location);
+        reflection.push_constant_ranges = push_constant_range.into_iter().collect();
+
+        reflection
+    }
+
+    ///由反射结果推导的绑定组布局,空缺的组号为空布局
+    pub fn bind_group_layout_infos(&self) -> Vec<BindGroupLayoutInfo> {
+        let count = self
+            .bind_groups
+            .keys()
+            .next_back()
+            .map_or(0, |group| group + 1);
+
+        (0..count)
+            .map(|group| BindGroupLayoutInfo {
+                label: Some(format!("reflected_bind_group_layout_{}", group).into()),
+                entries: self.bind_groups.get(&group).cloned().unwrap_or_default(),
+            })
+            .collect()
+    }
+
+    ///所有顶点输入紧密排列在一个顶点缓冲区中
+    pub fn vertex_buffer_layout(&self) -> Option<VertexBufferLayout> {
+        if self.vertex_inputs.is_empty() {
+            return None;
+        }
+
+        let mut offset = 0;
+        let mut attributes = vec![];
+
+        for input in self.vertex_inputs.iter() {
+            let format = input.format?;
+            attributes.push(VertexAttribute {
+                format,
+                offset,
+                shader_location: input.location,
+            });
+            offset += format.size();
+        }
+
+        Some(VertexBufferLayout {
+            array_stride: offset,
+            step_mode: VertexStepMode::Vertex,
+            attributes,
+        })
+    }
+
+    pub fn validate_bind_group_layouts(&self, layouts: &[BindGroupLayout]) -> Result<(), String> {
+        for (group, entries) in self.bind_groups.iter() {
+            let Some(layout) = layouts.get(*group as usize) else {
+                return Err(format!(
+                    "shader uses @group({}) but the pipeline layout has {} bind groups",
+                    group,
+                    layouts.len()
+                ));
+            };
+
+            for entry in entries.iter() {
+                let Some(layout_entry) = layout
+                    .get_desc()
+                    .entries
+                    .iter()
+                    .find(|layout_entry| layout_entry.binding == entry.binding)
+                else {
+                    return Err(format!(
+                        "@group({}) @binding({}) is missing from the bind group layout",
+                        group, entry.binding
+                    ));
+                };
+
+                if !layout_entry.visibility.contains(entry.visibility) {
+                    return Err(format!(
+                        "@group({}) @binding({}) is used by {:?} but only visible to {:?}",
+                        group, entry.binding, entry.visibility, layout_entry.visibility
+                    ));
+                }
+
+                if !is_binding_type_compatible(&entry.ty, &layout_entry.ty) {
+                    return Err(format!(
+                        "@group({}) @binding({}) expects {:?} but the layout declares {:?}",
+                        group, entry.binding, entry.ty, layout_entry.ty
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn validate_push_constant_ranges(
+        &self,
+        push_constant_ranges: &[PushConstantRange],
+    ) -> Result<(), String> {
+        for range in self.push_constant_ranges.iter() {
+            let covered = push_constant_ranges.iter().any(|declared| {
+                declared.stages.contains(range.stages)
+                    && declared.range.start <= range.range.start
+                    && declared.range.end >= range.range.end
+            });
+
+            if !covered {
+                return Err(format!(
+                    "shader uses {} bytes of push constants in {:?} but the layout declares {:?}",
+                    range.range.end, range.stages, push_constant_ranges
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn validate_vertex_buffers(&self, buffers: &[VertexBufferLayout]) -> Result<(), String> {
+        for input in self.vertex_inputs.iter() {
+            let Some(attribute) = buffers
+                .iter()
+                .flat_map(|buffer| buffer.attributes.iter())
+                .find(|attribute| attribute.shader_location == input.location)
+            else {
+                return Err(format!(
+                    "vertex input @location({}) is not provided by any vertex buffer",
+                    input.location
+                ));
+            };
+
+            if vertex_format_kind(attribute.format) != input.kind {
+                return Err(format!(
+                    "vertex input @location({}) expects {:?} data but the vertex buffer provides {:?}",
+                    input.location, input.kind, attribute.format
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn binding_type(
+    module: &naga::Module,
+    var: &naga::GlobalVariable,
+) -> Option<(BindingType, Option<NonZeroU32>)> {
+    let (inner, count) = match &module.types[var.ty].inner {
+        TypeInner::BindingArray { base, size } => (
+            &module.types[*base].inner,
+            match size {
+                ArraySize::Constant(size) => Some(*size),
+                _ => None,
+            },
+        ),
+        inner => (inner, None),
+    };
+
+    let ty = match var.space {
+        AddressSpace::Uniform => BindingType::Buffer {
+            ty: BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        AddressSpace::Storage { access } => BindingType::Buffer {
+            ty: BufferBindingType::Storage {
+                read_only: !access.contains(StorageAccess::STORE),
+            },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        AddressSpace::Handle => match inner {
+            TypeInner::Image {
+                dim,
+                arrayed,
+                class,
+            } => {
+                let view_dimension = view_dimension(*dim, *arrayed);
+
+                match class {
+                    ImageClass::Sampled { kind, multi } => BindingType::Texture {
+                        sample_type: match kind {
+                            ScalarKind::Float => TextureSampleType::Float { filterable: true },
+                            ScalarKind::Sint => TextureSampleType::Sint,
+                            ScalarKind::Uint => TextureSampleType::Uint,
+                            _ => return None,
+                        },
+                        view_dimension,
+                        multisampled: *multi,
+                    },
+                    ImageClass::Depth { multi } => BindingType::Texture {
+                        sample_type: TextureSampleType::Depth,
+                        view_dimension,
+                        multisampled: *multi,
+                    },
+                    ImageClass::Storage { format, access } => BindingType::StorageTexture {
+                        access: storage_texture_access(*access),
+                        format: storage_format(*format),
+                        view_dimension,
+                    },
+                }
+            }
+            TypeInner::Sampler { comparison } => BindingType::Sampler(if *comparison {
+                SamplerBindingType::Comparison
+            } else {
+                SamplerBindingType::Filtering
+            }),
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    Some((ty, count))
+}
+
+fn vertex_inputs(module: &naga::Module, function: &naga::Function) -> Vec<ReflectedVertexInput> {
+    let mut inputs = vec![];
+
+    for argument in function.arguments.iter() {
+        match (&argument.binding, &module.types[argument.ty].inner) {
+            (Some(Binding::Location { location, .. }), inner) => {
+                inputs.extend(vertex_input(*location, inner));
+            }
+            (None, TypeInner::Struct { members, .. }) => {
+                for member in members.iter() {
+                    if let Some(Binding::Location { location, .. }) = &member.binding {
+                        inputs.extend(vertex_input(*location, &module.types[member.ty].inner));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    inputs
+}
+
+fn vertex_input(location: u32, inner: &TypeInner) -> Option<ReflectedVertexInput> {
+    let (scalar, size) = match inner {
+        TypeInner::Scalar(scalar) => (*scalar, 1),
+        TypeInner::Vector { size, scalar } => (*scalar, *size as u32),
+        _ => return None,
+    };
+
+    let format = match (scalar.kind, scalar.width, size) {
+        (ScalarKind::Float, 4, 1) => Some(VertexFormat::Float32),
+        (ScalarKind::Float, 4, 2) => Some(VertexFormat::Float32x2),
+        (ScalarKind::Float, 4, 3) => Some(VertexFormat::Float32x3),
+        (ScalarKind::Float, 4, 4) => Some(VertexFormat::Float32x4),
+        (ScalarKind::Sint, 4, 1) => Some(VertexFormat::Sint32),
+        (ScalarKind::Sint, 4, 2) => Some(VertexFormat::Sint32x2),
+        (ScalarKind::Sint, 4, 3) => Some(VertexFormat::Sint32x3),
+        (ScalarKind::Sint, 4, 4) => Some(VertexFormat::Sint32x4),
+        (ScalarKind::Uint, 4, 1) => Some(VertexFormat::Uint32),
+        (ScalarKind::Uint, 4, 2) => Some(VertexFormat::Uint32x2),
+        (ScalarKind::Uint, 4, 3) => Some(VertexFormat::Uint32x3),
+        (ScalarKind::Uint, 4, 4) => Some(VertexFormat::Uint32x4),
+        _ => None,
+    };
+
+    Some(ReflectedVertexInput {
+        location,
+        kind: scalar.kind,
+        format,
+    })
+}
+
+fn vertex_format_kind(format: VertexFormat) -> ScalarKind {
+    match format {
+        VertexFormat::Uint8
+        | VertexFormat::Uint8x2
+        | VertexFormat::Uint8x4
+        | VertexFormat::Uint16
+        | VertexFormat::Uint16x2
+        | VertexFormat::Uint16x4
+        | VertexFormat::Uint32
+        | VertexFormat::Uint32x2
+        | VertexFormat::Uint32x3
+        | VertexFormat::Uint32x4 => ScalarKind::Uint,
+        VertexFormat::Sint8
+        | VertexFormat::Sint8x2
+        | VertexFormat::Sint8x4
+        | VertexFormat::Sint16
+        | VertexFormat::Sint16x2
+        | VertexFormat::Sint16x4
+        | VertexFormat::Sint32
+        | VertexFormat::Sint32x2
+        | VertexFormat::Sint32x3
+        | VertexFormat::Sint32x4 => ScalarKind::Sint,
+        _ => ScalarKind::Float,
+    }
+}
+
+fn is_binding_type_compatible(shader: &BindingType, layout: &BindingType) -> bool {
+    match (shader, layout) {
+        (
+            BindingType::Buffer {
+                ty: BufferBindingType::Uniform,
+                ..
+            },
+            BindingType::Buffer {
+                ty: BufferBindingType::Uniform,
+                ..
+            },
+        ) => true,
+        (
+            BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only: shader },
+                ..
+            },
+            BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only: layout },
+                ..
+            },
+        ) => *shader || !*layout,
+        (
+            BindingType::Texture {
+                sample_type: shader_sample_type,
+                view_dimension: shader_view_dimension,
+                multisampled: shader_multisampled,
+            },
+            BindingType::Texture {
+                sample_type: layout_sample_type,
+                view_dimension: layout_view_dimension,
+                multisampled: layout_multisampled,
+            },
+        ) => {
+            shader_view_dimension == layout_view_dimension
+                && shader_multisampled == layout_multisampled
+                && matches!(
+                    (shader_sample_type, layout_sample_type),
+                    (
+                        TextureSampleType::Float { .. },
+                        TextureSampleType::Float { .. }
+                    ) | (TextureSampleType::Float { .. }, TextureSampleType::Depth)
+                        | (TextureSampleType::Depth, TextureSampleType::Depth)
+                        | (TextureSampleType::Sint, TextureSampleType::Sint)
+                        | (TextureSampleType::Uint, TextureSampleType::Uint)
+                )
+        }
+        (BindingType::StorageTexture { .. }, BindingType::StorageTexture { .. }) => {
+            shader == layout
+        }
+        (BindingType::Sampler(shader), BindingType::Sampler(layout)) => {
+            (*shader == SamplerBindingType::Comparison)
+                == (*layout == SamplerBindingType::Comparison)
+        }
+        _ => false,
+    }
+}
+
+fn view_dimension(dim: ImageDimension, arrayed: bool) -> TextureViewDimension {
+    match (dim, arrayed) {
+        (ImageDimension::D1, _) => TextureViewDimension::D1,
+        (ImageDimension::D2, false) => TextureViewDimension::D2,
+        (ImageDimension::D2, true) => TextureViewDimension::D2Array,
+        (ImageDimension::D3, _) => TextureViewDimension::D3,
+        (ImageDimension::Cube, false) => TextureViewDimension::Cube,
+        (ImageDimension::Cube, true) => TextureViewDimension::CubeArray,
+    }
+}
+
+fn storage_texture_access(access: StorageAccess) -> StorageTextureAccess {
+    if access.contains(StorageAccess::ATOMIC) {
+        StorageTextureAccess::Atomic
+    } else if access.contains(StorageAccess::LOAD | StorageAccess::STORE) {
+        StorageTextureAccess::ReadWrite
+    } else if access.contains(StorageAccess::STORE) {
+        StorageTextureAccess::WriteOnly
+    } else {
+        StorageTextureAccess::ReadOnly
+    }
+}
+
+fn storage_format(format: StorageFormat) -> TextureFormat {
+    match format {
+        StorageFormat::R8Unorm => TextureFormat::R8Unorm,
+        StorageFormat::R8Snorm => TextureFormat::R8Snorm,
+        StorageFormat::R8Uint => TextureFormat::R8Uint,
+        StorageFormat::R8Sint => TextureFormat::R8Sint,
+        StorageFormat::R16Uint => TextureFormat::R16Uint,
+        StorageFormat::R16Sint => TextureFormat::R16Sint,
+        StorageFormat::R16Float => TextureFormat::R16Float,
+        StorageFormat::Rg8Unorm => TextureFormat::Rg8Unorm,
+        StorageFormat::Rg8Snorm => TextureFormat::Rg8Snorm,
+        StorageFormat::Rg8Uint => TextureFormat::Rg8Uint,
+        StorageFormat::Rg8Sint => TextureFormat::Rg8Sint,
+        StorageFormat::R32Uint => TextureFormat::R32Uint,
+        StorageFormat::R32Sint => TextureFormat::R32Sint,
+        StorageFormat::R32Float => TextureFormat::R32Float,
+        StorageFormat::Rg16Uint => TextureFormat::Rg16Uint,
+        StorageFormat::Rg16Sint => TextureFormat::Rg16Sint,
+        StorageFormat::Rg16Float => TextureFormat::Rg16Float,
+        StorageFormat::Rgba8Unorm => TextureFormat::Rgba8Unorm,
+        StorageFormat::Rgba8Snorm => TextureFormat::Rgba8Snorm,
+        StorageFormat::Rgba8Uint => TextureFormat::Rgba8Uint,
+        StorageFormat::Rgba8Sint => TextureFormat::Rgba8Sint,
+        StorageFormat::Bgra8Unorm => TextureFormat::Bgra8Unorm,
+        StorageFormat::Rgb10a2Uint => TextureFormat::Rgb10a2Uint,
+        StorageFormat::Rgb10a2Unorm => TextureFormat::Rgb10a2Unorm,
+        StorageFormat::Rg11b10Ufloat => TextureFormat::Rg11b10Ufloat,
+        StorageFormat::R64Uint => TextureFormat::R64Uint,
+        StorageFormat::Rg32Uint => TextureFormat::Rg32Uint,
+        StorageFormat::Rg32Sint => TextureFormat::Rg32Sint,
+        StorageFormat::Rg32Float => TextureFormat::Rg32Float,
+        StorageFormat::Rgba16Uint => TextureFormat::Rgba16Uint,
+        StorageFormat::Rgba16Sint => TextureFormat::Rgba16Sint,
+        StorageFormat::Rgba16Float => TextureFormat::Rgba16Float,
+        StorageFormat::Rgba32Uint => TextureFormat::Rgba32Uint,
+        StorageFormat::Rgba32Sint => TextureFormat::Rgba32Sint,
+        StorageFormat::Rgba32Float => TextureFormat::Rgba32Float,
+        StorageFormat::R16Unorm => TextureFormat::R16Unorm,
+        StorageFormat::R16Snorm => TextureFormat::R16Snorm,
+        StorageFormat::Rg16Unorm => TextureFormat::Rg16Unorm,
+        StorageFormat::Rg16Snorm => TextureFormat::Rg16Snorm,
+        StorageFormat::Rgba16Unorm => TextureFormat::Rgba16Unorm,
+        StorageFormat::Rgba16Snorm => TextureFormat::Rgba16Snorm,
+    }
+}
@@ -1,17 +1,38 @@
-use std::{collections::HashMap, fmt::Debug, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    sync::Arc,
+};
+
+use naga_oil::compose::{
+    ComposableModuleDescriptor, Composer, NagaModuleDescriptor, ShaderDefValue, ShaderLanguage,
+    ShaderType,
+};
 
 use crate::gfx_base::{
     cache::TemporaryCache,
     device::Device,
-    shader::{ShaderDefinition, ShaderResource},
-    shader_module::{ShaderModule, ShaderModuleDescriptor},
+    shader::{ShaderDefVal, ShaderDefinition, ShaderImport, ShaderResource},
+    shader_module::{ShaderModule, ShaderModuleDescriptor, ShaderModuleSource},
 };
 
-use super::CachedPipelineId;
+use super::{
+    CachedPipelineId, PUSH_CONSTANT_FALLBACK_GROUP, PUSH_CONSTANT_GROUP_SHADER_DEF,
+    PUSH_CONSTANTS_SHADER_DEF, PipelineCacheError, ShaderDiagnostic, ShaderReflection,
+};
+
+#[derive(Clone)]
+pub struct ProcessedShader {
+    pub module: Arc<ShaderModule>,
+    pub reflection: Arc<ShaderReflection>,
+}
 
+#[derive(Default)]
 pub struct ShaderData {
-    cached_pipeline_id: CachedPipelineId,
-    shader_module: Arc<ShaderModule>,
+    ///使用此着色器任意变体的管线
+    pipelines: HashSet<CachedPipelineId>,
+    ///以排序后的着色器宏定义区分的变体
+    processed_shaders: HashMap<Vec<ShaderDefVal>, ProcessedShader>,
 }
 
 #[derive(Default)]
@@ -19,6 +40,16 @@ pub struct ShaderCache {
     //todo 不需要ShaderDefinition
     pub(super) cache: TemporaryCache<ShaderDefinition>,
     data: HashMap<usize, ShaderData>,
+    composer: Composer,
+    capabilities: naga::valid::Capabilities,
+    ///由设备特性决定,所有着色器共用的宏定义
+    device_shader_defs: Vec<ShaderDefVal>,
+    ///可被导入的着色器
+    import_path_shaders: HashMap<ShaderImport, ShaderDefinition>,
+    ///直接导入某个着色器的着色器
+    dependents: HashMap<ShaderImport, HashSet<ShaderImport>>,
+    ///已创建着色器模块的着色器在缓存中的索引
+    import_path_indices: HashMap<ShaderImport, usize>,
 }
 
 impl Debug for ShaderCache {
@@ -27,18 +58,215 @@ impl Debug for ShaderCache {
     }
 }
 
+///与设备特性一致的naga校验能力
+fn get_capabilities(features: wgpu::Features) -> naga::valid::Capabilities {
+    use naga::valid::Capabilities;
+
+    let mut capabilities = Capabilities::default();
+
+    capabilities.set(
+        Capabilities::PUSH_CONSTANT,
+        features.contains(wgpu::Features::PUSH_CONSTANTS),
+    );
+    capabilities.set(
+        Capabilities::FLOAT64,
+        features.contains(wgpu::Features::SHADER_F64),
+    );
+    capabilities.set(
+        Capabilities::PRIMITIVE_INDEX,
+        features.contains(wgpu::Features::SHADER_PRIMITIVE_INDEX),
+    );
+    capabilities.set(
+        Capabilities::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING,
+        features.contains(
+            wgpu::Features::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING,
+        ),
+    );
+    capabilities.set(
+        Capabilities::MULTIVIEW,
+        features.contains(wgpu::Features::MULTIVIEW),
+    );
+    capabilities.set(
+        Capabilities::DUAL_SOURCE_BLENDING,
+        features.contains(wgpu::Features::DUAL_SOURCE_BLENDING),
+    );
+
+    capabilities
+}
+
+fn composer_shader_defs<'a>(
+    shader_defs: impl Iterator<Item = &'a ShaderDefVal>,
+) -> HashMap<String, ShaderDefValue> {
+    shader_defs
+        .map(|shader_def| (shader_def.name().to_string(), shader_def.composer_value()))
+        .collect()
+}
+
 impl ShaderCache {
+    pub fn new(device: &Device) -> Self {
+        let capabilities = get_capabilities(device.features());
+
+        let mut device_shader_defs = vec![ShaderDefVal::UInt(
+            PUSH_CONSTANT_GROUP_SHADER_DEF.to_string(),
+            PUSH_CONSTANT_FALLBACK_GROUP,
+        )];
+
+        if device.features().contains(wgpu::Features::PUSH_CONSTANTS) {
+            device_shader_defs.push(ShaderDefVal::Bool(
+                PUSH_CONSTANTS_SHADER_DEF.to_string(),
+                true,
+            ));
+        }
+
+        Self {
+            composer: Composer::default().with_capabilities(capabilities),
+            capabilities,
+            device_shader_defs,
+            ..Default::default()
+        }
+    }
+
+    ///将导入的着色器及其依赖注册到组合器
+    fn add_import_to_composer(
+        composer: &mut Composer,
+        import_path_shaders: &HashMap<ShaderImport, ShaderDefinition>,
+        import: &ShaderImport,
+    ) -> Result<(), PipelineCacheError> {
+        if composer.contains_module(&import.module_name()) {
+            return Ok(());
+        }
+
+        let definition = import_path_shaders
+            .get(import)
+            .ok_or(PipelineCacheError::ShaderImportNotYetAvailable)?;
+
+        for import in definition.imports.iter() {
+            Self::add_import_to_composer(composer, import_path_shaders, import)?;
+        }
+
+        let result = composer.add_composable_module(ComposableModuleDescriptor {
+            source: definition.source.as_str(),
+            file_path: &definition.path,
+            language: ShaderLanguage::Wgsl,
+            as_name: Some(import.module_name().into_owned()),
+            additional_imports: &[],
+            shader_defs: composer_shader_defs(definition.shader_defs.iter()),
+        });
+
+        if let Err(err) = result {
+            return Err(PipelineCacheError::ProcessShaderError(
+                ShaderDiagnostic::from_composer_error(composer, &err),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn register_import(&mut self, definition: &ShaderDefinition) {
+        self.unregister_import(&definition.import_path);
+
+        for import in definition.imports.iter() {
+            self.dependents
+                .entry(import.clone())
+                .or_default()
+                .insert(definition.import_path.clone());
+        }
+
+        self.import_path_shaders
+            .insert(definition.import_path.clone(), definition.clone());
+    }
+
+    fn unregister_import(&mut self, import_path: &ShaderImport) {
+        if let Some(definition) = self.import_path_shaders.remove(import_path) {
+            for import in definition.imports.iter() {
+                if let Some(dependents) = self.dependents.get_mut(import) {
+                    dependents.remove(import_path);
+                }
+            }
+        }
+    }
+
+    ///清除此着色器及所有直接或间接导入它的着色器,返回需要重新创建的管线
+    fn invalidate(&mut self, import_path: &ShaderImport) -> Vec<CachedPipelineId> {
+        let mut free_pipeline_ids = vec![];
+        let mut visited = HashSet::new();
+        let mut stack = vec![import_path.clone()];
+
+        while let Some(import_path) = stack.pop() {
+            if !visited.insert(import_path.clone()) {
+                continue;
+            }
+
+            self.composer
+                .remove_composable_module(&import_path.module_name());
+
+            if let Some(data) = self
+                .import_path_indices
+                .remove(&import_path)
+                .and_then(|index| self.data.remove(&index))
+            {
+                free_pipeline_ids.extend(data.pipelines);
+            }
+
+            if let Some(dependents) = self.dependents.get(&import_path) {
+                stack.extend(dependents.iter().cloned());
+            }
+        }
+
+        free_pipeline_ids
+    }
+
+    ///validate为false时组合器不校验最终模块,导入的模块始终校验
+    fn compose(
+        &mut self,
+        definition: &ShaderDefinition,
+        shader_defs: &[ShaderDefVal],
+        validate: bool,
+    ) -> Result<naga::Module, PipelineCacheError> {
+        for import in definition.imports.iter() {
+            Self::add_import_to_composer(&mut self.composer, &self.import_path_shaders, import)?;
+        }
+
+        self.composer.validate = validate;
+
+        let result = self.composer.make_naga_module(NagaModuleDescriptor {
+            source: definition.source.as_str(),
+            file_path: &definition.path,
+            shader_type: ShaderType::Wgsl,
+            shader_defs: composer_shader_defs(
+                self.device_shader_defs
+                    .iter()
+                    .chain(definition.shader_defs.iter())
+                    .chain(shader_defs.iter()),
+            ),
+            additional_imports: &[],
+        });
+
+        self.composer.validate = true;
+
+        result.map_err(|err| {
+            PipelineCacheError::ProcessShaderError(ShaderDiagnostic::from_composer_error(
+                &self.composer,
+                &err,
+            ))
+        })
+    }
+
     pub fn remove(&mut self, shader: &ShaderResource) -> Vec<CachedPipelineId> {
         let mut state = shader.state();
 
         let mut free_pipeline_ids = vec![];
 
         if let Some(shader_state) = state.data() {
-            self.cache.remove(&shader_state.cache_index);
-
             if let Some(data) = self.data.remove(&shader_state.cache_index.get()) {
-                free_pipeline_ids.push(data.cached_pipeline_id);
+                free_pipeline_ids.extend(data.pipelines);
             }
+
+            self.cache.remove(&shader_state.cache_index);
+
+            let import_path = &shader_state.definition.import_path;
+            free_pipeline_ids.extend(self.invalidate(import_path));
+            self.unregister_import(import_path);
         }
 
         free_pipeline_ids
@@ -49,6 +277,8 @@ impl ShaderCache {
         let mut free_pipeline_ids = vec![];
 
         if let Some(shader_state) = shader_state.data() {
+            self.register_import(&shader_state.definition);
+
             let _ = self.cache.get_or_insert_with(
                 &shader_state.cache_index,
                 Default::default(),
@@ -59,8 +289,10 @@ impl ShaderCache {
             );
 
             if let Some(data) = self.data.remove(&shader_state.cache_index.get()) {
-                free_pipeline_ids.push(data.cached_pipeline_id);
+                free_pipeline_ids.extend(data.pipelines);
             }
+
+            free_pipeline_ids.extend(self.invalidate(&shader_state.definition.import_path));
         }
 
         free_pipeline_ids
@@ -71,39 +303,97 @@ impl ShaderCache {
         device: &Device,
         pipeline: CachedPipelineId,
         shader: &ShaderResource,
-    ) -> Option<Arc<ShaderModule>> {
+        shader_defs: &[ShaderDefVal],
+    ) -> Result<ProcessedShader, PipelineCacheError> {
         let mut shader_state = shader.state();
-        if let Some(shader_state) = shader_state.data() {
-            if let Some(data) = self.data.get(&shader_state.cache_index.get()) {
-                Some(data.shader_module.clone())
-            } else {
-                let _ = self.cache.get_or_insert_with(
-                    &shader_state.cache_index,
-                    Default::default(),
-                    || {
-                        let target: Result<ShaderDefinition, ()> =
-                            Ok(shader_state.definition.clone());
-                        target
-                    },
-                );
-
-                let shader_module = Arc::new(device.create_shader_module(ShaderModuleDescriptor {
-                    label: None,
-                    source: shader_state.definition.clone(),
-                }));
-
-                self.data.insert(
-                    shader_state.cache_index.get(),
-                    ShaderData {
-                        cached_pipeline_id: pipeline,
-                        shader_module: shader_module.clone(),
-                    },
-                );
-
-                Some(shader_module)
+        let Some(shader_state) = shader_state.data() else {
+            return Err(PipelineCacheError::ShaderImportNotYetAvailable);
+        };
+
+        let mut shader_defs = shader_defs.to_vec();
+        shader_defs.sort();
+        shader_defs.dedup();
+
+        let _ =
+            self.cache
+                .get_or_insert_with(&shader_state.cache_index, Default::default(), || {
+                    let target: Result<ShaderDefinition, ()> = Ok(shader_state.definition.clone());
+                    target
+                });
+        let index = shader_state.cache_index.get();
+
+        //在处理着色器之前记录依赖,处理失败的管线也能在着色器变化后重新排队
+        self.import_path_indices
+            .insert(shader_state.definition.import_path.clone(), index);
+
+        let data = self.data.entry(index).or_default();
+        data.pipelines.insert(pipeline);
+
+        if let Some(processed_shader) = data.processed_shaders.get(&shader_defs) {
+            return Ok(processed_shader.clone());
+        }
+
+        if !self
+            .import_path_shaders
+            .contains_key(&shader_state.definition.import_path)
+        {
+            self.register_import(&shader_state.definition);
+        }
+
+        //组合器不返回校验信息,最终模块只在这里校验一次,校验信息用于反射
+        let module = self.compose(&shader_state.definition, &shader_defs, false)?;
+
+        let info = match naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            self.capabilities,
+        )
+        .validate(&module)
+        {
+            Ok(info) => info,
+            //校验失败时由组合器重新组合并校验,错误位置映射回对应的源码
+            Err(err) => {
+                return Err(self
+                    .compose(&shader_state.definition, &shader_defs, true)
+                    .err()
+                    .unwrap_or_else(|| {
+                        PipelineCacheError::ProcessShaderError(ShaderDiagnostic::new(
+                            shader_state.definition.path.clone(),
+                            err.to_string(),
+                        ))
+                    }));
             }
-        } else {
-            None
+        };
+        let reflection = Arc::new(ShaderReflection::new(&module, &info));
+
+        let shader_module = device
+            .create_shader_module(ShaderModuleDescriptor {
+                label: Some(shader_state.definition.path.clone().into()),
+                source: ShaderModuleSource::Naga(Box::new(module)),
+            })
+            .map_err(|err| {
+                PipelineCacheError::CreateShaderModule(ShaderDiagnostic::new(
+                    shader_state.definition.path.clone(),
+                    err.to_string(),
+                ))
+            })?;
+        let processed_shader = ProcessedShader {
+            module: Arc::new(shader_module),
+            reflection,
+        };
+
+        self.data
+            .entry(index)
+            .or_default()
+            .processed_shaders
+            .insert(shader_defs, processed_shader.clone());
+
+        Ok(processed_shader)
+    }
+
+    ///管线被移除后不再随着色器变化重新创建
+    pub fn remove_pipeline(&mut self, pipeline: CachedPipelineId) {
+        for data in self.data.values_mut() {
+            data.pipelines.remove(&pipeline);
         }
     }
 
@@ -112,12 +402,15 @@ impl ShaderCache {
 
         let mut free_pipeline_ids = vec![];
 
-        for free_shader_id in free_shader_ids {
-            if let Some(data) = self.data.remove(&free_shader_id) {
-                free_pipeline_ids.push(data.cached_pipeline_id);
+        for free_shader_id in free_shader_ids.iter() {
+            if let Some(data) = self.data.remove(free_shader_id) {
+                free_pipeline_ids.extend(data.pipelines);
             }
         }
 
+        self.import_path_indices
+            .retain(|_, index| !free_shader_ids.contains(index));
+
         free_pipeline_ids
     }
 
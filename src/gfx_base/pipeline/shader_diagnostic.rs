@@ -0,0 +1,100 @@
+use std::fmt::Display;
+
+use naga_oil::compose::{ComposerError, ComposerErrorInner};
+
+///naga_oil在span的高位记录模块索引
+const SPAN_SHIFT: usize = 21;
+
+///着色器错误及其在源码中的位置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShaderDiagnostic {
+    pub path: String,
+    ///从1开始的行号
+    pub line: Option<u32>,
+    ///从1开始的列号
+    pub column: Option<u32>,
+    pub message: String,
+    ///带源码片段的完整错误信息
+    pub rendered: String,
+}
+
+impl ShaderDiagnostic {
+    pub fn new(path: impl Into<String>, message: impl Into<String>) -> Self {
+        let message = message.into();
+
+        Self {
+            path: path.into(),
+            line: None,
+            column: None,
+            rendered: message.clone(),
+            message,
+        }
+    }
+
+    pub fn from_composer_error(
+        composer: &naga_oil::compose::Composer,
+        err: &ComposerError,
+    ) -> Self {
+        let path = err.source.path(composer).clone();
+        let source = err.source.source(composer);
+        let offset = err.source.offset();
+
+        let map_span = |span: naga::Span| {
+            span.to_range().map(|range| {
+                let start = (range.start & ((1 << SPAN_SHIFT) - 1)).saturating_sub(offset);
+                let end = (range.end & ((1 << SPAN_SHIFT) - 1)).saturating_sub(offset);
+                start..end
+            })
+        };
+
+        let range = match &err.inner {
+            ComposerErrorInner::WgslParseError(e) => {
+                e.labels().next().and_then(|(span, _)| map_span(span))
+            }
+            ComposerErrorInner::HeaderValidationError(v)
+            | ComposerErrorInner::ShaderValidationError(v) => {
+                v.spans().next().and_then(|(span, _)| map_span(*span))
+            }
+            ComposerErrorInner::InvalidIdentifier { at, .. } => map_span(*at),
+            ComposerErrorInner::DecorationInSource(range) => Some(range.clone()),
+            ComposerErrorInner::ImportNotFound(_, pos)
+            | ComposerErrorInner::ImportParseError(_, pos)
+            | ComposerErrorInner::NotEnoughEndIfs(pos)
+            | ComposerErrorInner::TooManyEndIfs(pos)
+            | ComposerErrorInner::ElseWithoutCondition(pos)
+            | ComposerErrorInner::UnknownShaderDef { pos, .. }
+            | ComposerErrorInner::UnknownShaderDefOperator { pos, .. }
+            | ComposerErrorInner::InvalidShaderDefComparisonValue { pos, .. }
+            | ComposerErrorInner::OverrideNotVirtual { pos, .. }
+            | ComposerErrorInner::GlslInvalidVersion(pos)
+            | ComposerErrorInner::DefineInModule(pos)
+            | ComposerErrorInner::InvalidShaderDefDefinitionValue { pos, .. } => Some(*pos..*pos),
+            _ => None,
+        };
+
+        let location = range.map(|range| {
+            let start = range.start.min(source.len());
+            let end = range.end.clamp(start, source.len());
+            naga::Span::new(start as u32, end as u32).location(&source)
+        });
+
+        Self {
+            path,
+            line: location.map(|location| location.line_number),
+            column: location.map(|location| location.line_position),
+            message: err.inner.to_string(),
+            rendered: err.emit_to_string(composer),
+        }
+    }
+}
+
+impl Display for ShaderDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(f, "{}:{}:{}: ", self.path, line, column)?,
+            _ => write!(f, "{}: ", self.path)?,
+        }
+
+        write!(f, "{}\n{}", self.message, self.rendered)
+    }
+}
@@ -0,0 +1,125 @@
+use std::{borrow::Cow, fmt::Debug};
+
+use downcast_rs::Downcast;
+
+use crate::{define_atomic_id, define_gfx_frame_graph_type};
+
+define_atomic_id!(QuerySetId);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QueryType {
+    Occlusion,
+    ///需要设备支持PIPELINE_STATISTICS_QUERY
+    PipelineStatistics(wgpu::PipelineStatisticsTypes),
+    Timestamp,
+}
+
+impl QueryType {
+    ///每个查询结果包含的u64数量
+    pub fn values_per_query(&self) -> u32 {
+        match self {
+            QueryType::PipelineStatistics(types) => types.bits().count_ones(),
+            _ => 1,
+        }
+    }
+
+    ///创建此类型的查询集需要的设备特性
+    pub fn required_features(&self) -> wgpu::Features {
+        match self {
+            QueryType::Occlusion => wgpu::Features::empty(),
+            QueryType::PipelineStatistics(_) => wgpu::Features::PIPELINE_STATISTICS_QUERY,
+            QueryType::Timestamp => wgpu::Features::TIMESTAMP_QUERY,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct QuerySetInfo {
+    pub label: Option<Cow<'static, str>>,
+    pub ty: QueryType,
+    pub count: u32,
+}
+
+pub trait QuerySetTrait: 'static + Debug + Clone + Sync + Send {
+    ///提交解析命令后请求回读查询结果
+    fn request_read(&self);
+
+    ///查询结果未就绪时返回空
+    fn try_read(&self) -> Option<Vec<u64>>;
+
+    ///是否有尚未读取的查询结果
+    fn is_pending(&self) -> bool;
+
+    ///丢弃尚未读取的查询结果,之后可以再次解析
+    fn reset(&self);
+}
+
+pub trait ErasedQuerySetTrait: 'static + Downcast + Debug + Sync + Send {
+    fn clone_value(&self) -> Box<dyn ErasedQuerySetTrait>;
+
+    fn request_read(&self);
+
+    fn try_read(&self) -> Option<Vec<u64>>;
+
+    fn is_pending(&self) -> bool;
+
+    fn reset(&self);
+}
+
+impl<T: QuerySetTrait> ErasedQuerySetTrait for T {
+    fn clone_value(&self) -> Box<dyn ErasedQuerySetTrait> {
+        Box::new(self.clone())
+    }
+
+    fn request_read(&self) {
+        <T as QuerySetTrait>::request_read(self)
+    }
+
+    fn try_read(&self) -> Option<Vec<u64>> {
+        <T as QuerySetTrait>::try_read(self)
+    }
+
+    fn is_pending(&self) -> bool {
+        <T as QuerySetTrait>::is_pending(self)
+    }
+
+    fn reset(&self) {
+        <T as QuerySetTrait>::reset(self)
+    }
+}
+
+define_gfx_frame_graph_type!(
+    QuerySet,
+    QuerySetId,
+    QuerySetTrait,
+    ErasedQuerySetTrait,
+    QuerySetInfo
+);
+
+impl Clone for QuerySet {
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id,
+            value: self.value.clone_value(),
+            desc: self.desc.clone(),
+        }
+    }
+}
+
+impl QuerySet {
+    pub fn request_read(&self) {
+        self.value.request_read();
+    }
+
+    pub fn try_read(&self) -> Option<Vec<u64>> {
+        self.value.try_read()
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.value.is_pending()
+    }
+
+    pub fn reset(&self) {
+        self.value.reset();
+    }
+}
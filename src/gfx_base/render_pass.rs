@@ -1,16 +1,27 @@
 use crate::{define_atomic_id, define_gfx_type, error::RendererError};
 use downcast_rs::Downcast;
-use std::fmt::Debug;
+use std::{borrow::Cow, fmt::Debug, sync::Arc};
 
-use crate::frame_graph::RenderContext;
+use crate::frame_graph::{GpuWrite, RenderContext, ResourceNodeRef};
 
-use super::color_attachment::ColorAttachmentInfo;
+use super::{QuerySet, color_attachment::ColorAttachmentInfo};
 
 define_atomic_id!(RenderPassId);
 
+///渲染通道和计算通道共用的时间戳写入
+#[derive(Clone)]
+pub struct PassTimestampWrites {
+    pub query_set: Arc<QuerySet>,
+    pub beginning_of_pass_write_index: Option<u32>,
+    pub end_of_pass_write_index: Option<u32>,
+}
+
 #[derive(Default, Clone)]
 pub struct RenderPassDescriptor {
+    pub label: Option<Cow<'static, str>>,
     pub color_attachments: Vec<ColorAttachmentInfo>,
+    pub timestamp_writes: Option<PassTimestampWrites>,
+    pub occlusion_query_set: Option<ResourceNodeRef<QuerySet, GpuWrite>>,
 }
 
 impl RenderPassDescriptor {}
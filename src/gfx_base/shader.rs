@@ -149,13 +149,31 @@ impl Source {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug, Hash, Reflect, Visit)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash, Reflect, Visit)]
 pub enum ShaderDefVal {
     Bool(String, bool),
     Int(String, i32),
     UInt(String, u32),
 }
 
+impl ShaderDefVal {
+    pub fn name(&self) -> &str {
+        match self {
+            ShaderDefVal::Bool(name, _)
+            | ShaderDefVal::Int(name, _)
+            | ShaderDefVal::UInt(name, _) => name,
+        }
+    }
+
+    pub fn composer_value(&self) -> naga_oil::compose::ShaderDefValue {
+        match self {
+            ShaderDefVal::Bool(_, value) => naga_oil::compose::ShaderDefValue::Bool(*value),
+            ShaderDefVal::Int(_, value) => naga_oil::compose::ShaderDefValue::Int(*value),
+            ShaderDefVal::UInt(_, value) => naga_oil::compose::ShaderDefValue::UInt(*value),
+        }
+    }
+}
+
 impl Default for ShaderDefVal {
     fn default() -> Self {
         Self::Bool("".to_string(), false)
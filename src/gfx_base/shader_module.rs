@@ -4,8 +4,6 @@ use downcast_rs::Downcast;
 
 use crate::{define_atomic_id, define_gfx_type};
 
-use super::shader::ShaderDefinition;
-
 define_atomic_id!(ShaderModuleId);
 
 pub trait ShaderModuleTrait: 'static + Debug + Sync + Send {}
@@ -20,7 +18,13 @@ define_gfx_type!(
     ErasedShaderModuleTrait
 );
 
+pub enum ShaderModuleSource {
+    Wgsl(Cow<'static, str>),
+    ///由naga_oil组合后的着色器
+    Naga(Box<naga::Module>),
+}
+
 pub struct ShaderModuleDescriptor {
     pub label: Option<Cow<'static, str>>,
-    pub source: ShaderDefinition,
+    pub source: ShaderModuleSource,
 }
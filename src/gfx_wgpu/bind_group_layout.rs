@@ -1,12 +1,19 @@
+use std::sync::Arc;
+
 use crate::gfx_base::bind_group_layout::BindGroupLayoutTrait;
 
+///引用计数用于判断布局是否只被设备的布局缓存持有
 #[derive(Debug, Clone)]
-pub struct WgpuBindGroupLayout(pub wgpu::BindGroupLayout);
+pub struct WgpuBindGroupLayout(pub Arc<wgpu::BindGroupLayout>);
 
 impl WgpuBindGroupLayout {
     pub fn bind_group_layout(&self) -> &wgpu::BindGroupLayout {
         &self.0
     }
+
+    pub fn is_unique(&self) -> bool {
+        Arc::strong_count(&self.0) == 1
+    }
 }
 
 impl BindGroupLayoutTrait for WgpuBindGroupLayout {}
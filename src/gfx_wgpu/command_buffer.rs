@@ -2,71 +2,225 @@ use std::ops::Range;
 
 use crate::{
     gfx_base::{
-        BindGroup, Buffer, command_buffer::CommandBufferTrait, device::Device,
-        pipeline::RenderPipeline, render_pass::RenderPass,
+        BindGroup, Buffer, ComputePassDescriptor, QuerySet,
+        command_buffer::CommandBufferTrait,
+        device::Device,
+        pipeline::{ComputePipeline, RenderPipeline},
+        render_pass::RenderPass,
+    },
+    gfx_wgpu::{
+        WgpuComputePipeline, WgpuRenderPipeline, WgpuTextureView, render_pass::WgpuRenderPass,
     },
-    gfx_wgpu::{WgpuDevice, WgpuRenderPipeline, WgpuTextureView, render_pass::WgpuRenderPass},
 };
 
-use super::{WgpuBindGroup, WgpuBuffer};
+use super::{WgpuBindGroup, WgpuBuffer, WgpuQuerySet};
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct WgpuCommandBuffer {
     encoder: Option<wgpu::CommandEncoder>,
     render_pass: Option<wgpu::RenderPass<'static>>,
+    compute_pass: Option<wgpu::ComputePass<'static>>,
     pub command_buffer: Option<wgpu::CommandBuffer>,
+    ///渲染通道结束后再解析的查询集
+    pending_resolves: Vec<(WgpuQuerySet, u32)>,
 }
 
-impl CommandBufferTrait for WgpuCommandBuffer {
-    fn begin_render_pass(&mut self, device: &Device, render_pass: RenderPass) {
-        let device = device.downcast_ref::<WgpuDevice>().unwrap();
+impl WgpuCommandBuffer {
+    pub fn new(encoder: wgpu::CommandEncoder) -> Self {
+        WgpuCommandBuffer {
+            encoder: Some(encoder),
+            render_pass: None,
+            compute_pass: None,
+            command_buffer: None,
+            pending_resolves: vec![],
+        }
+    }
 
+    fn end_pass(&mut self) {
+        self.render_pass = None;
+        self.compute_pass = None;
+
+        if let Some(encoder) = self.encoder.as_mut() {
+            for (query_set, count) in self.pending_resolves.drain(..) {
+                query_set.resolve(encoder, count);
+            }
+        }
+    }
+}
+
+impl CommandBufferTrait for WgpuCommandBuffer {
+    fn begin_render_pass(&mut self, _device: &Device, render_pass: RenderPass) {
         let mut render_pass = render_pass.downcast::<WgpuRenderPass>().unwrap();
 
         let mut color_attachments = vec![];
 
         let texture_views = render_pass.texture_views.take().unwrap();
 
-        for texture_view in texture_views.iter() {
+        for (texture_view, color_attachment) in texture_views
+            .iter()
+            .zip(render_pass.desc().color_attachments.iter())
+        {
             let texture_view = texture_view.downcast_ref::<WgpuTextureView>().unwrap();
 
             color_attachments.push(Some(wgpu::RenderPassColorAttachment {
                 view: &texture_view.0,
                 resolve_target: None,
-                ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color {
-                        r: 0.1,
-                        g: 0.2,
-                        b: 0.3,
-                        a: 1.0,
-                    }),
-                    store: wgpu::StoreOp::Store,
-                },
+                ops: *color_attachment.ops(),
             }));
         }
 
-        let mut encoder = device.device.create_command_encoder(&Default::default());
+        let timestamp_writes =
+            render_pass
+                .desc()
+                .timestamp_writes
+                .as_ref()
+                .map(|timestamp_writes| wgpu::RenderPassTimestampWrites {
+                    query_set: &timestamp_writes
+                        .query_set
+                        .downcast_ref::<WgpuQuerySet>()
+                        .unwrap()
+                        .query_set,
+                    beginning_of_pass_write_index: timestamp_writes.beginning_of_pass_write_index,
+                    end_of_pass_write_index: timestamp_writes.end_of_pass_write_index,
+                });
+
+        let occlusion_query_set = render_pass
+            .occlusion_query_set
+            .as_ref()
+            .map(|query_set| &query_set.downcast_ref::<WgpuQuerySet>().unwrap().query_set);
+
+        let encoder = self.encoder.as_mut().unwrap();
         let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("Render Pass"),
+            label: render_pass.desc().label.as_deref(),
             color_attachments: &color_attachments,
+            timestamp_writes,
+            occlusion_query_set,
             ..Default::default()
         });
 
         let render_pass = render_pass.forget_lifetime();
 
-        self.encoder = Some(encoder);
         self.render_pass = Some(render_pass);
     }
 
     fn end_render_pass(&mut self) {
-        let render_pass = self.render_pass.take().unwrap();
-        let encoder = self.encoder.take().unwrap();
+        self.end_pass();
+    }
 
-        drop(render_pass);
+    fn begin_compute_pass(&mut self, desc: &ComputePassDescriptor) {
+        let timestamp_writes = desc.timestamp_writes.as_ref().map(|timestamp_writes| {
+            wgpu::ComputePassTimestampWrites {
+                query_set: &timestamp_writes
+                    .query_set
+                    .downcast_ref::<WgpuQuerySet>()
+                    .unwrap()
+                    .query_set,
+                beginning_of_pass_write_index: timestamp_writes.beginning_of_pass_write_index,
+                end_of_pass_write_index: timestamp_writes.end_of_pass_write_index,
+            }
+        });
+
+        let encoder = self.encoder.as_mut().unwrap();
+        let compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: desc.label.as_deref(),
+            timestamp_writes,
+        });
+
+        self.compute_pass = Some(compute_pass.forget_lifetime());
+    }
+
+    fn set_compute_pipeline(&mut self, compute_pipeline: &ComputePipeline) {
+        let compute_pipeline = compute_pipeline
+            .downcast_ref::<WgpuComputePipeline>()
+            .unwrap();
+
+        if let Some(compute_pass) = self.compute_pass.as_mut() {
+            compute_pass.set_pipeline(&compute_pipeline.0);
+        }
+    }
 
-        let command_buffer = encoder.finish();
+    fn dispatch_workgroups(&mut self, x: u32, y: u32, z: u32) {
+        if let Some(compute_pass) = self.compute_pass.as_mut() {
+            compute_pass.dispatch_workgroups(x, y, z);
+        }
+    }
 
-        self.command_buffer = Some(command_buffer);
+    fn end_compute_pass(&mut self) {
+        self.end_pass();
+    }
+
+    fn resolve_query_set(&mut self, query_set: &QuerySet, count: u32) {
+        let query_set = query_set.downcast_ref::<WgpuQuerySet>().unwrap();
+
+        self.pending_resolves.push((query_set.clone(), count));
+
+        if self.render_pass.is_none() && self.compute_pass.is_none() {
+            self.end_pass();
+        }
+    }
+
+    fn begin_occlusion_query(&mut self, query_index: u32) {
+        if let Some(render_pass) = self.render_pass.as_mut() {
+            render_pass.begin_occlusion_query(query_index);
+        }
+    }
+
+    fn end_occlusion_query(&mut self) {
+        if let Some(render_pass) = self.render_pass.as_mut() {
+            render_pass.end_occlusion_query();
+        }
+    }
+
+    fn begin_pipeline_statistics_query(&mut self, query_set: &QuerySet, query_index: u32) {
+        let query_set = query_set.downcast_ref::<WgpuQuerySet>().unwrap();
+
+        if let Some(render_pass) = self.render_pass.as_mut() {
+            render_pass.begin_pipeline_statistics_query(&query_set.query_set, query_index);
+        }
+    }
+
+    fn end_pipeline_statistics_query(&mut self) {
+        if let Some(render_pass) = self.render_pass.as_mut() {
+            render_pass.end_pipeline_statistics_query();
+        }
+    }
+
+    fn push_debug_group(&mut self, label: &str) {
+        if let Some(render_pass) = self.render_pass.as_mut() {
+            render_pass.push_debug_group(label);
+        } else if let Some(compute_pass) = self.compute_pass.as_mut() {
+            compute_pass.push_debug_group(label);
+        } else if let Some(encoder) = self.encoder.as_mut() {
+            encoder.push_debug_group(label);
+        }
+    }
+
+    fn pop_debug_group(&mut self) {
+        if let Some(render_pass) = self.render_pass.as_mut() {
+            render_pass.pop_debug_group();
+        } else if let Some(compute_pass) = self.compute_pass.as_mut() {
+            compute_pass.pop_debug_group();
+        } else if let Some(encoder) = self.encoder.as_mut() {
+            encoder.pop_debug_group();
+        }
+    }
+
+    fn insert_debug_marker(&mut self, label: &str) {
+        if let Some(render_pass) = self.render_pass.as_mut() {
+            render_pass.insert_debug_marker(label);
+        } else if let Some(compute_pass) = self.compute_pass.as_mut() {
+            compute_pass.insert_debug_marker(label);
+        } else if let Some(encoder) = self.encoder.as_mut() {
+            encoder.insert_debug_marker(label);
+        }
+    }
+
+    fn finish(&mut self) {
+        self.end_pass();
+
+        if let Some(encoder) = self.encoder.take() {
+            self.command_buffer = Some(encoder.finish());
+        }
     }
 
     fn set_render_pipeline(&mut self, render_pipeline: &RenderPipeline) {
@@ -93,11 +247,26 @@ impl CommandBufferTrait for WgpuCommandBuffer {
         }
     }
 
-    fn set_bind_group(&mut self, index: u32, bind_group: &BindGroup) {
+    fn set_bind_group(
+        &mut self,
+        index: u32,
+        bind_group: &BindGroup,
+        offsets: &[wgpu::DynamicOffset],
+    ) {
         let bind_group = bind_group.downcast_ref::<WgpuBindGroup>().unwrap();
 
         if let Some(render_pass) = self.render_pass.as_mut() {
-            render_pass.set_bind_group(index, Some(&bind_group.0), &[]);
+            render_pass.set_bind_group(index, Some(&bind_group.0), offsets);
+        } else if let Some(compute_pass) = self.compute_pass.as_mut() {
+            compute_pass.set_bind_group(index, Some(&bind_group.0), offsets);
+        }
+    }
+
+    fn set_push_constants(&mut self, stages: wgpu::ShaderStages, offset: u32, data: &[u8]) {
+        if let Some(render_pass) = self.render_pass.as_mut() {
+            render_pass.set_push_constants(stages, offset, data);
+        } else if let Some(compute_pass) = self.compute_pass.as_mut() {
+            compute_pass.set_push_constants(offset, data);
         }
     }
 
@@ -114,4 +283,89 @@ impl CommandBufferTrait for WgpuCommandBuffer {
             render_pass.draw_indexed(indices, base_vertex, instances);
         }
     }
+
+    fn draw_indirect(&mut self, indirect_buffer: &Buffer, indirect_offset: u64) {
+        let indirect_buffer = indirect_buffer.downcast_ref::<WgpuBuffer>().unwrap();
+
+        if let Some(render_pass) = self.render_pass.as_mut() {
+            render_pass.draw_indirect(&indirect_buffer.buffer, indirect_offset);
+        }
+    }
+
+    fn draw_indexed_indirect(&mut self, indirect_buffer: &Buffer, indirect_offset: u64) {
+        let indirect_buffer = indirect_buffer.downcast_ref::<WgpuBuffer>().unwrap();
+
+        if let Some(render_pass) = self.render_pass.as_mut() {
+            render_pass.draw_indexed_indirect(&indirect_buffer.buffer, indirect_offset);
+        }
+    }
+
+    fn multi_draw_indirect(&mut self, indirect_buffer: &Buffer, indirect_offset: u64, count: u32) {
+        let indirect_buffer = indirect_buffer.downcast_ref::<WgpuBuffer>().unwrap();
+
+        if let Some(render_pass) = self.render_pass.as_mut() {
+            render_pass.multi_draw_indirect(&indirect_buffer.buffer, indirect_offset, count);
+        }
+    }
+
+    fn multi_draw_indexed_indirect(
+        &mut self,
+        indirect_buffer: &Buffer,
+        indirect_offset: u64,
+        count: u32,
+    ) {
+        let indirect_buffer = indirect_buffer.downcast_ref::<WgpuBuffer>().unwrap();
+
+        if let Some(render_pass) = self.render_pass.as_mut() {
+            render_pass.multi_draw_indexed_indirect(
+                &indirect_buffer.buffer,
+                indirect_offset,
+                count,
+            );
+        }
+    }
+
+    fn multi_draw_indirect_count(
+        &mut self,
+        indirect_buffer: &Buffer,
+        indirect_offset: u64,
+        count_buffer: &Buffer,
+        count_offset: u64,
+        max_count: u32,
+    ) {
+        let indirect_buffer = indirect_buffer.downcast_ref::<WgpuBuffer>().unwrap();
+        let count_buffer = count_buffer.downcast_ref::<WgpuBuffer>().unwrap();
+
+        if let Some(render_pass) = self.render_pass.as_mut() {
+            render_pass.multi_draw_indirect_count(
+                &indirect_buffer.buffer,
+                indirect_offset,
+                &count_buffer.buffer,
+                count_offset,
+                max_count,
+            );
+        }
+    }
+
+    fn multi_draw_indexed_indirect_count(
+        &mut self,
+        indirect_buffer: &Buffer,
+        indirect_offset: u64,
+        count_buffer: &Buffer,
+        count_offset: u64,
+        max_count: u32,
+    ) {
+        let indirect_buffer = indirect_buffer.downcast_ref::<WgpuBuffer>().unwrap();
+        let count_buffer = count_buffer.downcast_ref::<WgpuBuffer>().unwrap();
+
+        if let Some(render_pass) = self.render_pass.as_mut() {
+            render_pass.multi_draw_indexed_indirect_count(
+                &indirect_buffer.buffer,
+                indirect_offset,
+                &count_buffer.buffer,
+                count_offset,
+                max_count,
+            );
+        }
+    }
 }
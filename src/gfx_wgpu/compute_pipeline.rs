@@ -0,0 +1,12 @@
+use crate::gfx_base::pipeline::ComputePipelineTrait;
+
+#[derive(Debug)]
+pub struct WgpuComputePipeline(pub wgpu::ComputePipeline);
+
+impl WgpuComputePipeline {
+    pub fn new(pipeline: wgpu::ComputePipeline) -> Self {
+        WgpuComputePipeline(pipeline)
+    }
+}
+
+impl ComputePipelineTrait for WgpuComputePipeline {}
@@ -1,23 +1,36 @@
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    pin::pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
 use wgpu::util::DeviceExt;
 
 use crate::{
+    error::RendererError,
     gfx_base::{
-        BindGroup, BindGroupInfo, BindGroupLayout, BindGroupLayoutInfo, BindingResource, Sample,
-        SampleInfo, Texture, TextureInfo,
+        BindGroup, BindGroupInfo, BindGroupLayout, BindGroupLayoutCache, BindGroupLayoutInfo,
+        BindingResource, QuerySet, QuerySetInfo, QueryType, Sample, SampleInfo, Texture,
+        TextureInfo,
         buffer::{Buffer, BufferInfo, BufferInitInfo},
-        command_buffer::CommandBuffer,
-        device::DeviceTrait,
-        pipeline::{RenderPipeline, RenderPipelineDescriptorState},
+        command_buffer::{CommandBuffer, CommandBufferInfo, CommandBufferTrait},
+        device::{DeviceTrait, QueueType},
+        pipeline::{
+            ComputePipeline, ComputePipelineDescriptorState, RenderPipeline,
+            RenderPipelineDescriptorState,
+        },
         pipeline_layout::{PipelineLayout, PipelineLayoutDescriptor},
         render_pass::{RenderPass, RenderPassDescriptor},
-        shader_module::{ShaderModule, ShaderModuleDescriptor},
+        shader_module::{ShaderModule, ShaderModuleDescriptor, ShaderModuleSource},
     },
     gfx_wgpu::{WgpuBindGroup, WgpuBindGroupLayout, WgpuBuffer},
 };
 
 use super::{
-    WgpuCommandBuffer, WgpuPipelineLayout, WgpuRenderPipeline, WgpuSample, WgpuShaderModule,
-    WgpuTextureView, render_pass::WgpuRenderPass, texture::WgpuTexture,
+    WgpuCommandBuffer, WgpuComputePipeline, WgpuPipelineLayout, WgpuQuerySet, WgpuRenderPipeline,
+    WgpuSample, WgpuShaderModule, WgpuTextureView, render_pass::WgpuRenderPass,
+    texture::WgpuTexture,
 };
 
 #[derive(Debug)]
@@ -25,11 +38,24 @@ pub struct WgpuDevice {
     pub device: wgpu::Device,
 
     queue: wgpu::Queue,
+    adapter_info: wgpu::AdapterInfo,
+    pipeline_cache: Mutex<Option<wgpu::PipelineCache>>,
+    bind_group_layout_cache: BindGroupLayoutCache,
 }
 
 impl WgpuDevice {
-    pub fn new(device: wgpu::Device, queue: wgpu::Queue) -> Self {
-        WgpuDevice { device, queue }
+    pub fn new(device: wgpu::Device, queue: wgpu::Queue, adapter_info: wgpu::AdapterInfo) -> Self {
+        WgpuDevice {
+            device,
+            queue,
+            adapter_info,
+            pipeline_cache: Mutex::new(None),
+            bind_group_layout_cache: BindGroupLayoutCache::default(),
+        }
+    }
+
+    fn pipeline_cache(&self) -> Option<wgpu::PipelineCache> {
+        self.pipeline_cache.lock().unwrap().clone()
     }
 }
 
@@ -38,11 +64,94 @@ impl DeviceTrait for WgpuDevice {
         RenderPass::new(WgpuRenderPass::new(desc))
     }
 
-    fn submit(&self, command_buffers: Vec<CommandBuffer>) {
+    fn create_query_set(&self, desc: QuerySetInfo) -> QuerySet {
+        let ty = match desc.ty {
+            QueryType::Occlusion => wgpu::QueryType::Occlusion,
+            QueryType::PipelineStatistics(types) => wgpu::QueryType::PipelineStatistics(types),
+            QueryType::Timestamp => wgpu::QueryType::Timestamp,
+        };
+
+        let query_set = self.device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: desc.label.as_deref(),
+            ty,
+            count: desc.count,
+        });
+
+        QuerySet::new(
+            WgpuQuerySet::new(
+                &self.device,
+                query_set,
+                desc.count,
+                desc.ty.values_per_query(),
+            ),
+            desc,
+        )
+    }
+
+    fn features(&self) -> wgpu::Features {
+        self.device.features()
+    }
+
+    fn timestamp_period(&self) -> f32 {
+        self.queue.get_timestamp_period()
+    }
+
+    fn pipeline_cache_key(&self) -> Option<String> {
+        if !self
+            .device
+            .features()
+            .contains(wgpu::Features::PIPELINE_CACHE)
+        {
+            return None;
+        }
+
+        let key = wgpu::util::pipeline_cache_key(&self.adapter_info)?;
+
+        //驱动更新后旧的缓存文件不再使用
+        let mut hasher = DefaultHasher::new();
+        self.adapter_info.driver.hash(&mut hasher);
+        self.adapter_info.driver_info.hash(&mut hasher);
+
+        Some(format!("{}_{:016x}", key, hasher.finish()))
+    }
+
+    fn init_pipeline_cache(&self, data: Option<&[u8]>) {
+        if !self
+            .device
+            .features()
+            .contains(wgpu::Features::PIPELINE_CACHE)
+        {
+            return;
+        }
+
+        //fallback为true时驱动会忽略无效的数据
+        let pipeline_cache = unsafe {
+            self.device
+                .create_pipeline_cache(&wgpu::PipelineCacheDescriptor {
+                    label: Some("pipeline_cache"),
+                    data,
+                    fallback: true,
+                })
+        };
+
+        *self.pipeline_cache.lock().unwrap() = Some(pipeline_cache);
+    }
+
+    fn pipeline_cache_data(&self) -> Option<Vec<u8>> {
+        self.pipeline_cache()?.get_data()
+    }
+
+    fn has_queue(&self, queue_type: QueueType) -> bool {
+        //wgpu只暴露一个队列
+        queue_type == QueueType::Graphics
+    }
+
+    fn submit(&self, _queue_type: QueueType, command_buffers: Vec<CommandBuffer>) {
         let mut targets = vec![];
 
         for command_buffer in command_buffers.into_iter() {
             let mut command_buffer = command_buffer.downcast::<WgpuCommandBuffer>().unwrap();
+            command_buffer.finish();
 
             if let Some(command_buffer) = command_buffer.command_buffer.take() {
                 targets.push(command_buffer);
@@ -97,6 +206,7 @@ impl DeviceTrait for WgpuDevice {
         });
 
         let label = state.desc.label.as_ref().map(|label| label.to_string());
+        let pipeline_cache = self.pipeline_cache();
 
         let render_pipeline = self
             .device
@@ -123,19 +233,60 @@ impl DeviceTrait for WgpuDevice {
                     alpha_to_coverage_enabled: false,
                 },
                 multiview: None,
-                cache: None,
+                cache: pipeline_cache.as_ref(),
             });
 
         RenderPipeline::new(WgpuRenderPipeline::new(render_pipeline))
     }
 
-    fn create_command_buffer(&self) -> CommandBuffer {
-        CommandBuffer::new(WgpuCommandBuffer::default())
+    fn create_compute_pipeline(&self, state: ComputePipelineDescriptorState) -> ComputePipeline {
+        let module = state.module.downcast_ref::<WgpuShaderModule>().unwrap();
+
+        let layout = state.layout.as_ref().map(|layout| {
+            layout
+                .downcast_ref::<WgpuPipelineLayout>()
+                .unwrap()
+                .pipeline_layout()
+        });
+
+        let pipeline_cache = self.pipeline_cache();
+
+        let compute_pipeline =
+            self.device
+                .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    label: state.desc.label.as_deref(),
+                    layout,
+                    module: module.shader_module(),
+                    entry_point: Some(&state.desc.entry_point),
+                    compilation_options: Default::default(),
+                    cache: pipeline_cache.as_ref(),
+                });
+
+        ComputePipeline::new(WgpuComputePipeline::new(compute_pipeline))
     }
 
-    fn create_shader_module(&self, desc: ShaderModuleDescriptor) -> ShaderModule {
-        let source =
-            wgpu::ShaderSource::Wgsl(std::borrow::Cow::Borrowed(desc.source.source.as_str()));
+    fn create_command_buffer(&self, desc: CommandBufferInfo) -> CommandBuffer {
+        let encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: desc.label.as_deref(),
+            });
+
+        CommandBuffer::new(WgpuCommandBuffer::new(encoder))
+    }
+
+    fn create_shader_module(
+        &self,
+        desc: ShaderModuleDescriptor,
+    ) -> Result<ShaderModule, RendererError> {
+        let source = match desc.source {
+            ShaderModuleSource::Wgsl(source) => wgpu::ShaderSource::Wgsl(source),
+            ShaderModuleSource::Naga(module) => {
+                wgpu::ShaderSource::Naga(std::borrow::Cow::Owned(*module))
+            }
+        };
+
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
 
         let shader_module = self
             .device
@@ -144,7 +295,15 @@ impl DeviceTrait for WgpuDevice {
                 source,
             });
 
-        ShaderModule::new(WgpuShaderModule::new(shader_module))
+        //原生后端的错误作用域立即返回结果
+        let error_scope = self.device.pop_error_scope();
+        let mut context = Context::from_waker(Waker::noop());
+
+        if let Poll::Ready(Some(error)) = pin!(error_scope).poll(&mut context) {
+            return Err(RendererError::CreateShaderModule(error.to_string()));
+        }
+
+        Ok(ShaderModule::new(WgpuShaderModule::new(shader_module)))
     }
 
     fn create_pipeline_layout(&self, desc: PipelineLayoutDescriptor) -> PipelineLayout {
@@ -202,6 +361,11 @@ impl DeviceTrait for WgpuDevice {
         )
     }
 
+    fn write_buffer(&self, buffer: &Buffer, offset: wgpu::BufferAddress, data: &[u8]) {
+        let buffer = buffer.downcast_ref::<WgpuBuffer>().unwrap();
+        self.queue.write_buffer(&buffer.buffer, offset, data);
+    }
+
     fn create_texture(&self, desc: TextureInfo) -> Texture {
         let texture = self.device.create_texture(&wgpu::TextureDescriptor {
             label: desc.label.as_deref(),
@@ -209,7 +373,7 @@ impl DeviceTrait for WgpuDevice {
             mip_level_count: 1,
             sample_count: 1,
             dimension: desc.dimension,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            format: desc.format,
             usage: desc.usage,
             view_formats: &[],
         });
@@ -224,14 +388,24 @@ impl DeviceTrait for WgpuDevice {
         )
     }
 
+    fn collect_bind_group_layouts(&self) {
+        self.bind_group_layout_cache.collect(|layout| {
+            layout
+                .downcast_ref::<WgpuBindGroupLayout>()
+                .is_some_and(WgpuBindGroupLayout::is_unique)
+        });
+    }
+
     fn create_bind_group_layout(&self, desc: BindGroupLayoutInfo) -> BindGroupLayout {
-        let bind_group_layout =
-            self.device
-                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                    label: desc.label.as_deref(),
-                    entries: &desc.entries,
-                });
-        BindGroupLayout::new(WgpuBindGroupLayout(bind_group_layout))
+        self.bind_group_layout_cache.get_or_create(desc, |desc| {
+            let bind_group_layout =
+                self.device
+                    .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                        label: desc.label.as_deref(),
+                        entries: &desc.entries,
+                    });
+            BindGroupLayout::new(WgpuBindGroupLayout(Arc::new(bind_group_layout)), desc)
+        })
     }
 
     fn create_bind_group(&self, desc: BindGroupInfo) -> BindGroup {
@@ -277,6 +451,24 @@ fn get_binding_resource(source: &BindingResource) -> wgpu::BindingResource {
             wgpu::BindingResource::TextureView(&res.0)
         }
 
+        BindingResource::Buffer(res) => {
+            let res = res.downcast_ref::<WgpuBuffer>().unwrap();
+            res.buffer.as_entire_binding()
+        }
+
+        BindingResource::BufferRange {
+            buffer,
+            offset,
+            size,
+        } => {
+            let buffer = buffer.downcast_ref::<WgpuBuffer>().unwrap();
+            wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                buffer: &buffer.buffer,
+                offset: *offset,
+                size: Some(*size),
+            })
+        }
+
         BindingResource::Sampler(res) => {
             let res = res.downcast_ref::<WgpuSample>().unwrap();
             wgpu::BindingResource::Sampler(&res.0)
@@ -2,8 +2,10 @@ pub mod bind_group;
 pub mod bind_group_layout;
 pub mod buffer;
 pub mod command_buffer;
+pub mod compute_pipeline;
 pub mod device;
 pub mod pipeline_layout;
+pub mod query_set;
 pub mod render_pass;
 pub mod render_pipeline;
 pub mod sample;
@@ -15,8 +17,10 @@ pub use bind_group::*;
 pub use bind_group_layout::*;
 pub use buffer::*;
 pub use command_buffer::*;
+pub use compute_pipeline::*;
 pub use device::*;
 pub use pipeline_layout::*;
+pub use query_set::*;
 pub use render_pipeline::*;
 pub use sample::*;
 pub use shader_module::*;
@@ -0,0 +1,137 @@
+use std::sync::{Arc, Mutex};
+
+use crate::gfx_base::query_set::QuerySetTrait;
+
+#[derive(Debug, Clone, PartialEq)]
+enum QueryReadState {
+    Idle,
+    Resolved,
+    Mapping,
+    Mapped,
+}
+
+#[derive(Debug, Clone)]
+pub struct WgpuQuerySet {
+    pub query_set: wgpu::QuerySet,
+    pub resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    device: wgpu::Device,
+    ///每个查询结果的字节数
+    query_size: u64,
+    state: Arc<Mutex<QueryReadState>>,
+}
+
+impl WgpuQuerySet {
+    pub fn new(
+        device: &wgpu::Device,
+        query_set: wgpu::QuerySet,
+        count: u32,
+        values_per_query: u32,
+    ) -> Self {
+        let query_size = values_per_query as u64 * wgpu::QUERY_SIZE as u64;
+        let size = count as u64 * query_size;
+
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("query_resolve_buffer"),
+            size,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("query_readback_buffer"),
+            size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        WgpuQuerySet {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+            device: device.clone(),
+            query_size,
+            state: Arc::new(Mutex::new(QueryReadState::Idle)),
+        }
+    }
+
+    ///录制解析命令,映射中的结果未读取前不会覆盖回读缓冲
+    pub fn resolve(&self, encoder: &mut wgpu::CommandEncoder, count: u32) {
+        let mut state = self.state.lock().unwrap();
+
+        if *state != QueryReadState::Idle {
+            return;
+        }
+
+        encoder.resolve_query_set(&self.query_set, 0..count, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.readback_buffer,
+            0,
+            count as u64 * self.query_size,
+        );
+
+        *state = QueryReadState::Resolved;
+    }
+}
+
+impl QuerySetTrait for WgpuQuerySet {
+    fn request_read(&self) {
+        let mut state = self.state.lock().unwrap();
+
+        if *state != QueryReadState::Resolved {
+            return;
+        }
+
+        *state = QueryReadState::Mapping;
+
+        let callback_state = self.state.clone();
+        self.readback_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                *callback_state.lock().unwrap() = match result {
+                    Ok(_) => QueryReadState::Mapped,
+                    Err(_) => QueryReadState::Idle,
+                };
+            });
+    }
+
+    fn try_read(&self) -> Option<Vec<u64>> {
+        self.device.poll(wgpu::Maintain::Poll);
+
+        let mut state = self.state.lock().unwrap();
+
+        if *state != QueryReadState::Mapped {
+            return None;
+        }
+
+        let data = {
+            let view = self.readback_buffer.slice(..).get_mapped_range();
+            bytemuck::cast_slice::<u8, u64>(&view).to_vec()
+        };
+
+        self.readback_buffer.unmap();
+        *state = QueryReadState::Idle;
+
+        Some(data)
+    }
+
+    fn is_pending(&self) -> bool {
+        *self.state.lock().unwrap() != QueryReadState::Idle
+    }
+
+    ///映射中的回读无法取消,等映射完成后由try_read读取
+    fn reset(&self) {
+        let mut state = self.state.lock().unwrap();
+
+        match *state {
+            QueryReadState::Resolved => *state = QueryReadState::Idle,
+            QueryReadState::Mapped => {
+                self.readback_buffer.unmap();
+                *state = QueryReadState::Idle;
+            }
+            QueryReadState::Idle | QueryReadState::Mapping => {}
+        }
+    }
+}
@@ -2,7 +2,7 @@ use crate::{
     error::RendererError,
     frame_graph::RenderContext,
     gfx_base::{
-        ColorAttachmentInfo,
+        ColorAttachmentInfo, QuerySet,
         render_pass::{RenderPassDescriptor, RenderPassTrait},
         texture_view::TextureView,
     },
@@ -11,6 +11,7 @@ use crate::{
 pub struct WgpuRenderPass {
     desc: RenderPassDescriptor,
     pub texture_views: Option<Vec<TextureView>>,
+    pub occlusion_query_set: Option<QuerySet>,
 }
 
 impl WgpuRenderPass {
@@ -18,8 +19,13 @@ impl WgpuRenderPass {
         WgpuRenderPass {
             desc,
             texture_views: None,
+            occlusion_query_set: None,
         }
     }
+
+    pub fn desc(&self) -> &RenderPassDescriptor {
+        &self.desc
+    }
 }
 
 impl RenderPassTrait for WgpuRenderPass {
@@ -28,7 +34,7 @@ impl RenderPassTrait for WgpuRenderPass {
 
         for color_attachment in self.desc.color_attachments.iter() {
             match color_attachment {
-                ColorAttachmentInfo::SwapChain(handle) => {
+                ColorAttachmentInfo::SwapChain { handle, .. } => {
                     if let Some(resource) = render_context.get_resource(handle) {
                         texture_views.push(resource.clone());
                     } else {
@@ -42,6 +48,17 @@ impl RenderPassTrait for WgpuRenderPass {
 
         self.texture_views = Some(texture_views);
 
+        if let Some(handle) = &self.desc.occlusion_query_set {
+            let query_set = render_context
+                .resource_table
+                .get_resource::<QuerySet>(&handle.resource_handle())
+                .ok_or(RendererError::ResourceNotFound {
+                    resource_index: handle.resource_handle().index(),
+                })?;
+
+            self.occlusion_query_set = Some(query_set.clone());
+        }
+
         Ok(())
     }
 }
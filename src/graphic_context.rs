@@ -119,6 +119,20 @@ impl GraphicContext {
         )));
     }
 
+    pub fn world_renderer_mut(&mut self) -> Option<&mut WorldRenderer> {
+        match self {
+            GraphicContext::Initialization(context) => Some(&mut context.world_renderer),
+            GraphicContext::Uninitialization(_) => None,
+        }
+    }
+
+    pub fn pipeline_cache_mut(&mut self) -> Option<&mut PipelineCache> {
+        match self {
+            GraphicContext::Initialization(context) => Some(&mut context.pipeline_cache),
+            GraphicContext::Uninitialization(_) => None,
+        }
+    }
+
     pub fn render(&mut self, dt: f32, cameras: &[RenderCamera]) {
         if let GraphicContext::Initialization(context) = self {
             context.render(dt, cameras)
@@ -1,13 +1,15 @@
 use std::{mem, sync::Arc};
 
 use image::GenericImageView;
+use tracing::error;
 use wgpu::{BufferUsages, ColorTargetState, TextureFormat};
 
 use crate::{
     build_in::get_test,
+    frame_graph::{GpuRead, ResourceNodeRef},
     gfx_base::{
-        BindGroupEntryInfo, BindGroupLayout, BindGroupLayoutInfo, BindGroupRef,
-        BindingResourceInfo, BufferInitInfo, ColorAttachmentInfo, SampleInfo, TextureInfo,
+        BindGroupEntryInfo, BindGroupRef, BindingResourceInfo, Buffer, BufferInitInfo,
+        ColorAttachmentInfo, SampleInfo, TextureInfo,
         pipeline::{
             CachedRenderPipelineId, FragmentState, PipelineCache, RenderPipelineDescriptor,
             VertexBufferLayout, VertexState,
@@ -58,21 +60,25 @@ impl Image {
     }
 }
 
+#[derive(Clone)]
+pub struct MeshPassData {
+    pub vertex_buffer: ResourceNodeRef<Buffer, GpuRead>,
+    pub index_buffer: ResourceNodeRef<Buffer, GpuRead>,
+    pub bind_group: BindGroupRef,
+}
+
 pub struct MeshMaterial {
     pub vertex_buffers: Vec<Vertex>,
     pub indexes: Vec<u16>,
     pub image: Image,
     pub id: Option<CachedRenderPipelineId>,
-    pub texture_bind_group_layout: Option<BindGroupLayout>,
 }
 
 impl Renderer for MeshMaterial {
     fn prepare(&self, context: &mut FrameGraphContext) {
-        if self.id.is_none() || self.texture_bind_group_layout.is_none() {
+        let Some(pipeline_id) = self.id else {
             return;
-        }
-        let pipeline_id = self.id.unwrap();
-        let texture_bind_group_layout = self.texture_bind_group_layout.clone().unwrap();
+        };
 
         if context
             .pipeline_cache
@@ -81,6 +87,15 @@ impl Renderer for MeshMaterial {
         {
             return;
         }
+
+        //绑定组布局由着色器反射得到
+        let Some(texture_bind_group_layout) = context
+            .pipeline_cache
+            .get_render_pipeline_bind_group_layout(&pipeline_id, 0)
+            .cloned()
+        else {
+            return;
+        };
         let num_indices = self.indexes.len() as u32;
 
         let buffer = context.device.create_buffer_init(BufferInitInfo {
@@ -106,47 +121,68 @@ impl Renderer for MeshMaterial {
 
         let texture = Arc::new(texture);
 
-        let mut builder = context.frame_graph.create_pass_node_builder(2, "vertex");
-
-        let texture_handle = builder.import("texture", texture);
-        let texture_read = builder.read(texture_handle);
-
-        let swap_chain_handle = builder.import("swap_chain", context.camera.get_texture_view());
-        let swap_chain_read = builder.read(swap_chain_handle);
-
-        let index_buffer_handle = builder.import("index_buffer", index_buffer);
-        let index_buffer_read = builder.read(index_buffer_handle);
-
-        let vertex_buffer_handle = builder.import("vertex_buffer", vertex_buffer);
-        let vertex_buffer_read = builder.read(vertex_buffer_handle);
-
-        builder.add_attachment_info(ColorAttachmentInfo::SwapChain(swap_chain_read));
-
-        let bind_group = BindGroupRef {
-            label: Some("diffuse_bind_group".into()),
-            layout: texture_bind_group_layout,
-            entries: vec![
-                BindGroupEntryInfo {
-                    binding: 0,
-                    resource: BindingResourceInfo::TextureView(texture_read.clone()),
-                },
-                BindGroupEntryInfo {
-                    binding: 1,
-                    resource: BindingResourceInfo::Sampler(SampleInfo {}),
-                },
-            ],
-            index: 0,
-        };
-
-        builder.render(move |render_context| {
-            render_context.set_render_pipeline(&pipeline_id);
-            render_context.set_bind_group(0, &bind_group);
-            render_context.set_vertex_buffer(0, vertex_buffer_read);
-            render_context.set_index_buffer(index_buffer_read, wgpu::IndexFormat::Uint16);
-            render_context.draw_indexed(0..num_indices, 0, 0..1);
+        let result = context.frame_graph.add_pass(
+            2,
+            "vertex",
+            |builder| {
+                let texture_handle = builder.import("texture", texture)?;
+                let texture_read = builder.read(texture_handle);
+
+                let swap_chain_handle =
+                    builder.import("swap_chain", context.camera.get_texture_view())?;
+                let swap_chain_read = builder.read(swap_chain_handle);
+
+                let index_buffer_handle = builder.import("index_buffer", index_buffer)?;
+                let index_buffer_read = builder.read(index_buffer_handle);
+
+                let vertex_buffer_handle = builder.import("vertex_buffer", vertex_buffer)?;
+                let vertex_buffer_read = builder.read(vertex_buffer_handle);
+
+                builder.add_attachment_info(ColorAttachmentInfo::swap_chain_with_ops(
+                    swap_chain_read,
+                    wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                ));
+
+                let bind_group = BindGroupRef {
+                    label: Some("diffuse_bind_group".into()),
+                    layout: texture_bind_group_layout,
+                    entries: vec![
+                        BindGroupEntryInfo {
+                            binding: 0,
+                            resource: BindingResourceInfo::TextureView(texture_read),
+                        },
+                        BindGroupEntryInfo {
+                            binding: 1,
+                            resource: BindingResourceInfo::Sampler(SampleInfo {}),
+                        },
+                    ],
+                    index: 0,
+                };
+
+                Ok(MeshPassData {
+                    vertex_buffer: vertex_buffer_read,
+                    index_buffer: index_buffer_read,
+                    bind_group,
+                })
+            },
+            move |pass_data, render_context| {
+                render_context.set_render_pipeline(&pipeline_id);
+                render_context.set_bind_group(0, &pass_data.bind_group);
+                render_context.set_vertex_buffer(0, pass_data.vertex_buffer.clone());
+                render_context
+                    .set_index_buffer(pass_data.index_buffer.clone(), wgpu::IndexFormat::Uint16);
+                render_context.draw_indexed(0..num_indices, 0, 0..1);
+
+                Ok(())
+            },
+        );
 
-            Ok(())
-        });
+        if let Err(e) = result {
+            error!("failed to add mesh pass: {}", e);
+        }
     }
 }
 
@@ -156,33 +192,6 @@ impl MeshesRender for MeshMaterial {
             return;
         }
 
-        let texture_bind_group_layout =
-            pipeline_cache
-                .device
-                .create_bind_group_layout(BindGroupLayoutInfo {
-                    label: Some("texture_bind_group_layout".into()),
-                    entries: vec![
-                        wgpu::BindGroupLayoutEntry {
-                            binding: 0,
-                            visibility: wgpu::ShaderStages::FRAGMENT,
-                            ty: wgpu::BindingType::Texture {
-                                multisampled: false,
-                                view_dimension: wgpu::TextureViewDimension::D2,
-                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                            },
-                            count: None,
-                        },
-                        wgpu::BindGroupLayoutEntry {
-                            binding: 1,
-                            visibility: wgpu::ShaderStages::FRAGMENT,
-                            // This should match the filterable field of the
-                            // corresponding Texture entry above.
-                            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                            count: None,
-                        },
-                    ],
-                });
-
         let vertex_buffer_layout = VertexBufferLayout {
             array_stride: core::mem::size_of::<Vertex>() as wgpu::BufferAddress,
             step_mode: wgpu::VertexStepMode::Vertex,
@@ -221,12 +230,11 @@ impl MeshesRender for MeshMaterial {
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
             }),
-            layout: vec![texture_bind_group_layout.clone()],
+            layout: vec![],
             push_constant_ranges: vec![],
         };
 
         self.id = Some(pipeline_cache.register_render_pipeline(test_desc));
-        self.texture_bind_group_layout = Some(texture_bind_group_layout);
     }
 }
 
@@ -236,7 +244,6 @@ impl MeshMaterial {
             vertex_buffers,
             id: None,
             image: Image::new(),
-            texture_bind_group_layout: None,
             indexes,
         }
     }
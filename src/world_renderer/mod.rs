@@ -8,7 +8,9 @@ use std::sync::Arc;
 
 use crate::gfx_base::device::Device;
 
-use crate::frame_graph::{FrameGraph, RenderContext, TransientResourceCache};
+use crate::frame_graph::{
+    FrameGraph, FrameTimings, PushConstantBuffer, RenderContext, TransientResourceCache,
+};
 use crate::gfx_base::pipeline::PipelineCache;
 use crate::gfx_base::texture_view::TextureView;
 
@@ -42,7 +44,14 @@ impl RenderCamera {
 pub struct WorldRenderer {
     device: Arc<Device>,
     transient_resource_cache: TransientResourceCache,
+    push_constant_buffer: PushConstantBuffer,
     schedules: RenderSchedules,
+    ///每个相机对应的帧图
+    frame_graphs: Vec<FrameGraph>,
+    ///跨帧保留帧图的编译结果
+    retain_frame_graph: bool,
+    ///统计每个渲染节点的耗时
+    gpu_profiling: bool,
 }
 
 impl WorldRenderer {
@@ -50,23 +59,60 @@ impl WorldRenderer {
         WorldRenderer {
             device: pipeline_cache.device.clone(),
             transient_resource_cache: TransientResourceCache::default(),
+            push_constant_buffer: PushConstantBuffer::default(),
             schedules: RenderSchedules::new(pipeline_cache),
+            frame_graphs: vec![],
+            retain_frame_graph: false,
+            gpu_profiling: false,
         }
     }
 
+    pub fn set_retain_frame_graph(&mut self, retain_frame_graph: bool) {
+        self.retain_frame_graph = retain_frame_graph;
+
+        for frame_graph in self.frame_graphs.iter_mut() {
+            frame_graph.set_retain_compiled(retain_frame_graph);
+        }
+    }
+
+    pub fn set_gpu_profiling(&mut self, gpu_profiling: bool) {
+        self.gpu_profiling = gpu_profiling;
+
+        for frame_graph in self.frame_graphs.iter_mut() {
+            frame_graph.set_profiling(gpu_profiling);
+        }
+    }
+
+    ///相机对应帧图最近一帧已完成的耗时
+    pub fn frame_timings(&self, camera_index: usize) -> Option<&FrameTimings> {
+        self.frame_graphs
+            .get(camera_index)
+            .and_then(|frame_graph| frame_graph.frame_timings())
+    }
+
     pub fn render(
         &mut self,
         pipeline_cache: &mut PipelineCache,
         cameras: &[RenderCamera],
         mesh_material: &MeshMaterial,
     ) {
-        for camera in cameras.iter() {
-            let mut frame_graph = FrameGraph::default();
+        self.frame_graphs.truncate(cameras.len());
+        self.push_constant_buffer.reset();
+
+        for (index, camera) in cameras.iter().enumerate() {
+            if self.frame_graphs.len() <= index {
+                let mut frame_graph = FrameGraph::default();
+                frame_graph.set_retain_compiled(self.retain_frame_graph);
+                frame_graph.set_profiling(self.gpu_profiling);
+                self.frame_graphs.push(frame_graph);
+            }
+
+            let frame_graph = &mut self.frame_graphs[index];
 
             let mut context = FrameGraphContext {
                 device: &self.device,
                 camera,
-                frame_graph: &mut frame_graph,
+                frame_graph,
                 pipeline_cache,
             };
 
@@ -80,6 +126,7 @@ impl WorldRenderer {
                 &self.device,
                 pipeline_cache,
                 &mut self.transient_resource_cache,
+                &mut self.push_constant_buffer,
             );
 
             frame_graph.execute(&mut render_context);
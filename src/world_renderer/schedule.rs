@@ -1,6 +1,7 @@
 use std::{collections::HashMap, sync::Arc};
 
 use downcast_rs::{Downcast, impl_downcast};
+use tracing::error;
 
 use crate::gfx_base::{ColorAttachmentInfo, device::Device, pipeline::PipelineCache};
 
@@ -92,11 +93,19 @@ impl RenderScheduleTrait for CameraDriverSchedule {
             .frame_graph
             .create_pass_node_builder(1, "camera_driver");
 
-        let swap_chain_handle = builder.import("swap_chain", context.camera.get_texture_view());
+        let swap_chain_handle =
+            match builder.import("swap_chain", context.camera.get_texture_view()) {
+                Ok(handle) => handle,
+                Err(e) => {
+                    error!("failed to import swap chain: {}", e);
+                    builder.cancel();
+                    return;
+                }
+            };
 
         let swap_chain_read = builder.read(swap_chain_handle);
 
-        builder.add_attachment_info(ColorAttachmentInfo::SwapChain(swap_chain_read));
+        builder.add_attachment_info(ColorAttachmentInfo::swap_chain(swap_chain_read));
 
         builder.render(|_render_context| Ok(()));
     }